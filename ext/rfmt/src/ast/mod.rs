@@ -180,6 +180,12 @@ pub enum NodeType {
     KeywordRestParameterNode,
     BlockParameterNode,
 
+    // Block parameters (`|a, b; c|`, or implicit `_1` and `it`)
+    BlockParametersNode,
+    BlockLocalVariableNode,
+    NumberedParametersNode,
+    ItParametersNode,
+
     // Source metadata nodes
     SourceFileNode,
     SourceLineNode,
@@ -271,6 +277,10 @@ impl std::str::FromStr for NodeType {
             "optional_keyword_parameter_node" => Self::OptionalKeywordParameterNode,
             "keyword_rest_parameter_node" => Self::KeywordRestParameterNode,
             "block_parameter_node" => Self::BlockParameterNode,
+            "block_parameters_node" => Self::BlockParametersNode,
+            "block_local_variable_node" => Self::BlockLocalVariableNode,
+            "numbered_parameters_node" => Self::NumberedParametersNode,
+            "it_parameters_node" => Self::ItParametersNode,
             "symbol_node" => Self::SymbolNode,
             "case_node" => Self::CaseNode,
             "when_node" => Self::WhenNode,
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Complete configuration structure matching .rfmt.yml format
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,8 +41,20 @@ pub struct FormattingConfig {
     #[serde(rename = "quote_style", default)]
     pub quote_style: QuoteStyle,
 
+    #[serde(default)]
+    pub end_of_line: EndOfLine,
+
     #[serde(default)]
     pub style: StyleConfig,
+
+    #[serde(default)]
+    pub blank_lines: BlankLinesConfig,
+
+    #[serde(default)]
+    pub gemfile: GemfileConfig,
+
+    #[serde(default)]
+    pub comments: CommentsConfig,
 }
 
 fn default_line_length() -> usize {
@@ -60,6 +73,88 @@ pub enum IndentStyle {
     Tabs,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    /// Match the first line ending found in the input
+    #[default]
+    Auto,
+}
+
+/// Blank lines between the statements of class, module and method bodies
+///
+/// Blank lines right after the opening line (`class Foo`) and before `end` are always
+/// removed; elsewhere at most one blank line is kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlankLinesConfig {
+    /// Require a blank line between consecutive method definitions
+    #[serde(default)]
+    pub between_methods: bool,
+
+    /// Require a blank line after a run of `include`/`extend`/`prepend`/`attr_*` calls
+    #[serde(default)]
+    pub after_macros: bool,
+
+    /// Blank lines before and after bare `private`, `protected` and `public`
+    #[serde(default)]
+    pub around_access_modifiers: BlankLineRule,
+}
+
+/// Normalization of `#` comments on their own line or after code
+///
+/// Comments inside code that is copied verbatim, such as a multi-line hash literal,
+/// are left alone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommentsConfig {
+    /// Write `#comment` as `# comment`
+    #[serde(default)]
+    pub space_after_hash: bool,
+
+    /// Remove whitespace at the end of comment lines
+    #[serde(default)]
+    pub strip_trailing_whitespace: bool,
+
+    /// Refill paragraphs of prose comments to `line_length`
+    #[serde(default)]
+    pub reflow: bool,
+
+    /// Rewrite `=begin`/`=end` comments as `#` lines
+    #[serde(default)]
+    pub convert_block_comments: bool,
+
+    /// Line up the trailing comments of consecutive lines in one column
+    #[serde(default)]
+    pub align_trailing_comments: bool,
+}
+
+/// Extra rules for files named `Gemfile`, `gems.rb` or `*.gemspec`
+///
+/// `gem` and `add_*dependency` calls in these files always get their arguments
+/// separated by `, `.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GemfileConfig {
+    /// Sort runs of `gem` lines by name, at the top level and inside `group`,
+    /// `source` and `platforms` blocks
+    #[serde(default)]
+    pub sort_gems: bool,
+
+    /// Warn about gems declared more than once
+    #[serde(default)]
+    pub check_duplicates: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlankLineRule {
+    /// Keep a blank line where the source has one
+    #[default]
+    Preserve,
+    Always,
+    Never,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StyleConfig {
     #[serde(default)]
@@ -70,6 +165,89 @@ pub struct StyleConfig {
 
     #[serde(default)]
     pub trailing_comma: TrailingComma,
+
+    #[serde(default)]
+    pub method_style: MethodStyle,
+
+    #[serde(default)]
+    pub block_delimiters: BlockDelimiters,
+
+    #[serde(default)]
+    pub case_when: CaseWhen,
+
+    /// Delimiters for percent literals keyed by type, e.g. `%w: "[]"`; a `default`
+    /// entry applies to types without their own
+    #[serde(default)]
+    pub percent_literal_delimiters: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub word_arrays: WordArrays,
+
+    #[serde(default)]
+    pub numeric_literals: NumericLiteralsConfig,
+
+    #[serde(default)]
+    pub access_modifier_indentation: AccessModifierIndentation,
+
+    /// Sort runs of `require`, `require_relative` and `autoload` lines: stdlib, gems,
+    /// then relative paths, alphabetically within each group
+    #[serde(default)]
+    pub sort_requires: bool,
+
+    /// Add `# frozen_string_literal: true` to files that do not declare it
+    #[serde(default)]
+    pub insert_frozen_string_literal: bool,
+
+    /// Line up the `=` of consecutive one-line variable and constant assignments
+    #[serde(default)]
+    pub align_assignments: bool,
+
+    /// Alignment of the pairs of multi-line hashes
+    #[serde(default)]
+    pub align_hash_values: HashAlignment,
+}
+
+/// Indentation of bare `private`, `protected` and `public` in class and module bodies
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessModifierIndentation {
+    /// Keep the layout of the source: outdented, indented sections, or flat
+    #[default]
+    Preserve,
+    /// Put modifiers at the level of `class`/`module`
+    Outdent,
+    /// Indent the methods after a modifier one level deeper than it
+    Indent,
+}
+
+/// Normalization of integer and float literals
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NumericLiteralsConfig {
+    /// Group decimal integers with at least this many digits with `_` (`1_000_000`);
+    /// unset keeps them as written
+    #[serde(default)]
+    pub underscore_min_digits: Option<usize>,
+
+    /// Case of the digits in hex literals (`0xFF`)
+    #[serde(default)]
+    pub hex_digits: LetterCase,
+
+    /// Case of the `0x`, `0b`, `0o` and `0d` prefixes
+    #[serde(default)]
+    pub prefix: LetterCase,
+
+    /// Case of the exponent marker in floats (`1.5e3`)
+    #[serde(default)]
+    pub exponent: LetterCase,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LetterCase {
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -98,6 +276,77 @@ pub enum TrailingComma {
     Multiline,
 }
 
+/// Layout of method definitions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MethodStyle {
+    /// Keep endless (`def foo = bar`) and single-line (`def foo; end`) definitions
+    #[default]
+    Preserve,
+    /// Always use `def ... end` spanning several lines
+    Expanded,
+    /// Turn single-expression methods into endless definitions when they fit on a line
+    Endless,
+}
+
+/// Choice between `{ }` and `do...end` for blocks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockDelimiters {
+    /// Keep the delimiters used in the source
+    #[default]
+    Preserve,
+    /// Braces for blocks that fit on one line, `do...end` for the rest
+    LineCountBased,
+    /// Braces for blocks whose value is used (`map`, `select`, ...), `do...end` for
+    /// blocks run for their side effects (`each`, `tap`, ...)
+    Semantic,
+}
+
+/// Layout of `when` and `in` branches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseWhen {
+    /// Keep one-line `when x then y` branches on one line
+    #[default]
+    Preserve,
+    /// Always put the body of a branch on its own lines
+    Expand,
+    /// Keep one-line branches that fit in `line_length` and line up their `then`
+    CompactAligned,
+}
+
+/// Form of arrays whose elements are all simple words
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordArrays {
+    /// Keep `%w[a b]` and `['a', 'b']` as written
+    #[default]
+    Preserve,
+    /// Write word arrays as `%w[a b]`
+    Percent,
+    /// Write word arrays as `['a', 'b']`
+    Brackets,
+}
+
+/// Alignment of hash pairs on consecutive lines, after RuboCop's `Layout/HashAlignment`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlignment {
+    /// Keep the spacing as written
+    #[default]
+    Preserve,
+    /// One space after `key:`, and around `=>`
+    Key,
+    /// Keys right-aligned so the separators line up
+    Separator,
+    /// Keys left-aligned, separators and values in columns
+    Table,
+}
+
+/// Percent literal types accepted as `percent_literal_delimiters` keys
+pub const PERCENT_LITERAL_TYPES: &[&str] = &["default", "%", "%q", "%Q", "%w", "%W", "%i", "%I"];
+
 impl Config {
     /// Discover configuration file in current directory or parent directories
     /// Searches in order: rfmt.yml, rfmt.yaml, .rfmt.yml, .rfmt.yaml
@@ -177,6 +426,32 @@ impl Config {
             });
         }
 
+        for (literal, delimiters) in &self.formatting.style.percent_literal_delimiters {
+            if !PERCENT_LITERAL_TYPES.contains(&literal.as_str()) {
+                return Err(RfmtError::ConfigError {
+                    message: format!(
+                        "percent_literal_delimiters: unknown literal type {:?}, expected one of {}",
+                        literal,
+                        PERCENT_LITERAL_TYPES.join(", ")
+                    ),
+                });
+            }
+            if delimiters.chars().count() != 2 {
+                return Err(RfmtError::ConfigError {
+                    message: format!(
+                        "percent_literal_delimiters: {} must be an opening and closing character, got {:?}",
+                        literal, delimiters
+                    ),
+                });
+            }
+        }
+
+        if self.formatting.style.numeric_literals.underscore_min_digits == Some(0) {
+            return Err(RfmtError::ConfigError {
+                message: "numeric_literals: underscore_min_digits must be at least 1".to_string(),
+            });
+        }
+
         Ok(())
     }
 
@@ -259,7 +534,11 @@ impl Default for FormattingConfig {
             indent_style: IndentStyle::Spaces,
             indent_width: 2,
             quote_style: QuoteStyle::Double,
+            end_of_line: EndOfLine::Auto,
             style: StyleConfig::default(),
+            blank_lines: BlankLinesConfig::default(),
+            gemfile: GemfileConfig::default(),
+            comments: CommentsConfig::default(),
         }
     }
 }
@@ -270,6 +549,17 @@ impl Default for StyleConfig {
             quotes: QuoteStyle::Double,
             hash_syntax: HashSyntax::Ruby19,
             trailing_comma: TrailingComma::Multiline,
+            method_style: MethodStyle::Preserve,
+            block_delimiters: BlockDelimiters::Preserve,
+            case_when: CaseWhen::Preserve,
+            percent_literal_delimiters: BTreeMap::new(),
+            word_arrays: WordArrays::Preserve,
+            numeric_literals: NumericLiteralsConfig::default(),
+            access_modifier_indentation: AccessModifierIndentation::Preserve,
+            sort_requires: false,
+            insert_frozen_string_literal: false,
+            align_assignments: false,
+            align_hash_values: HashAlignment::Preserve,
         }
     }
 }
@@ -399,6 +689,220 @@ formatting:
         assert!(!config.should_include(Path::new("test/foo.rb")));
     }
 
+    #[test]
+    fn test_end_of_line() {
+        assert!(matches!(
+            Config::default().formatting.end_of_line,
+            EndOfLine::Auto
+        ));
+
+        let config: Config = serde_yaml::from_str("formatting:\n  end_of_line: crlf\n").unwrap();
+        assert!(matches!(config.formatting.end_of_line, EndOfLine::Crlf));
+    }
+
+    #[test]
+    fn test_method_style() {
+        assert!(matches!(
+            Config::default().formatting.style.method_style,
+            MethodStyle::Preserve
+        ));
+
+        let yaml = "formatting:\n  style:\n    method_style: endless\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(
+            config.formatting.style.method_style,
+            MethodStyle::Endless
+        ));
+    }
+
+    #[test]
+    fn test_block_delimiters() {
+        assert!(matches!(
+            Config::default().formatting.style.block_delimiters,
+            BlockDelimiters::Preserve
+        ));
+
+        let yaml = "formatting:\n  style:\n    block_delimiters: line_count_based\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(
+            config.formatting.style.block_delimiters,
+            BlockDelimiters::LineCountBased
+        ));
+    }
+
+    #[test]
+    fn test_case_when() {
+        assert!(matches!(
+            Config::default().formatting.style.case_when,
+            CaseWhen::Preserve
+        ));
+
+        let yaml = "formatting:\n  style:\n    case_when: compact_aligned\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(
+            config.formatting.style.case_when,
+            CaseWhen::CompactAligned
+        ));
+    }
+
+    #[test]
+    fn test_percent_literal_delimiters() {
+        let style = Config::default().formatting.style;
+        assert!(style.percent_literal_delimiters.is_empty());
+        assert!(matches!(style.word_arrays, WordArrays::Preserve));
+
+        let yaml = "formatting:\n  style:\n    percent_literal_delimiters:\n      default: \"()\"\n      \"%w\": \"[]\"\n    word_arrays: percent\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let delimiters = &config.formatting.style.percent_literal_delimiters;
+        assert_eq!(delimiters["%w"], "[]");
+        assert_eq!(delimiters["default"], "()");
+        assert!(matches!(
+            config.formatting.style.word_arrays,
+            WordArrays::Percent
+        ));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_percent_literal_delimiters() {
+        let mut config = Config::default();
+        config
+            .formatting
+            .style
+            .percent_literal_delimiters
+            .insert("%w".to_string(), "[".to_string());
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config
+            .formatting
+            .style
+            .percent_literal_delimiters
+            .insert("%x".to_string(), "()".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let numeric = Config::default().formatting.style.numeric_literals;
+        assert_eq!(numeric.underscore_min_digits, None);
+        assert!(matches!(numeric.hex_digits, LetterCase::Preserve));
+
+        let yaml = "formatting:\n  style:\n    numeric_literals:\n      underscore_min_digits: 5\n      hex_digits: upper\n      exponent: lower\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let numeric = &config.formatting.style.numeric_literals;
+        assert_eq!(numeric.underscore_min_digits, Some(5));
+        assert!(matches!(numeric.hex_digits, LetterCase::Upper));
+        assert!(matches!(numeric.prefix, LetterCase::Preserve));
+        assert!(matches!(numeric.exponent, LetterCase::Lower));
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let blank_lines = Config::default().formatting.blank_lines;
+        assert!(!blank_lines.between_methods);
+        assert!(!blank_lines.after_macros);
+        assert!(matches!(
+            blank_lines.around_access_modifiers,
+            BlankLineRule::Preserve
+        ));
+
+        let yaml = "formatting:\n  blank_lines:\n    between_methods: true\n    around_access_modifiers: always\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let blank_lines = &config.formatting.blank_lines;
+        assert!(blank_lines.between_methods);
+        assert!(!blank_lines.after_macros);
+        assert!(matches!(
+            blank_lines.around_access_modifiers,
+            BlankLineRule::Always
+        ));
+    }
+
+    #[test]
+    fn test_gemfile() {
+        let gemfile = Config::default().formatting.gemfile;
+        assert!(!gemfile.sort_gems);
+        assert!(!gemfile.check_duplicates);
+
+        let yaml = "formatting:\n  gemfile:\n    sort_gems: true\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.formatting.gemfile.sort_gems);
+        assert!(!config.formatting.gemfile.check_duplicates);
+    }
+
+    #[test]
+    fn test_comments() {
+        let comments = Config::default().formatting.comments;
+        assert!(!comments.space_after_hash);
+        assert!(!comments.strip_trailing_whitespace);
+        assert!(!comments.reflow);
+        assert!(!comments.convert_block_comments);
+        assert!(!comments.align_trailing_comments);
+
+        let yaml = "formatting:\n  comments:\n    space_after_hash: true\n    reflow: true\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.formatting.comments.space_after_hash);
+        assert!(config.formatting.comments.reflow);
+        assert!(!config.formatting.comments.convert_block_comments);
+    }
+
+    #[test]
+    fn test_access_modifier_indentation() {
+        assert!(matches!(
+            Config::default()
+                .formatting
+                .style
+                .access_modifier_indentation,
+            AccessModifierIndentation::Preserve
+        ));
+
+        let yaml = "formatting:\n  style:\n    access_modifier_indentation: outdent\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(
+            config.formatting.style.access_modifier_indentation,
+            AccessModifierIndentation::Outdent
+        ));
+    }
+
+    #[test]
+    fn test_sort_requires() {
+        assert!(!Config::default().formatting.style.sort_requires);
+
+        let yaml = "formatting:\n  style:\n    sort_requires: true\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.formatting.style.sort_requires);
+    }
+
+    #[test]
+    fn test_insert_frozen_string_literal() {
+        assert!(
+            !Config::default()
+                .formatting
+                .style
+                .insert_frozen_string_literal
+        );
+
+        let yaml = "formatting:\n  style:\n    insert_frozen_string_literal: true\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.formatting.style.insert_frozen_string_literal);
+    }
+
+    #[test]
+    fn test_alignment() {
+        let style = Config::default().formatting.style;
+        assert!(!style.align_assignments);
+        assert!(matches!(style.align_hash_values, HashAlignment::Preserve));
+
+        let yaml =
+            "formatting:\n  style:\n    align_assignments: true\n    align_hash_values: table\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.formatting.style.align_assignments);
+        assert!(matches!(
+            config.formatting.style.align_hash_values,
+            HashAlignment::Table
+        ));
+    }
+
     #[test]
     fn test_invalid_yaml_syntax() {
         let yaml = r#"
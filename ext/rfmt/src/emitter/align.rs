@@ -0,0 +1,380 @@
+use super::literal::{Layout, Literal};
+use crate::ast::{Node, NodeType};
+use crate::config::{HashAlignment, StyleConfig};
+
+/// Where the padding of an aligned entry goes
+#[derive(Clone, Copy)]
+enum Pad {
+    /// No padding, only the separator is normalized
+    None,
+    /// After the name, so that the separators line up
+    After,
+    /// Before the name, so that names end in one column
+    Before,
+}
+
+/// An entry to line up with its neighbours: `source[start..end]` is the name and
+/// separator before a value, rewritten as `name + separator` plus padding
+struct Entry<'a> {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    /// Width of the whole source line
+    width: usize,
+    name: &'a str,
+    separator: &'static str,
+}
+
+/// Replacement literals that align `=` in runs of assignments under
+/// `align_assignments` and the pairs of multi-line hashes under `align_hash_values`
+///
+/// A run is a sequence of single-line statements (or pairs of one hash) on
+/// consecutive lines. A run whose widest line would exceed `line_length` once
+/// aligned is left as written, as are pairs whose key is already rewritten by
+/// `literals`.
+pub(super) fn collect_alignments(
+    ast: &Node,
+    source: &str,
+    style: &StyleConfig,
+    line_length: usize,
+    literals: &[Literal],
+) -> Vec<Literal> {
+    let mut alignments = Vec::new();
+    let mut stack = vec![ast];
+    while let Some(node) = stack.pop() {
+        match node.node_type {
+            NodeType::StatementsNode if style.align_assignments => {
+                let entries: Vec<_> = node
+                    .children
+                    .iter()
+                    .map(|child| assignment(child, source))
+                    .collect();
+                for run in runs(&entries) {
+                    align(run, source, Pad::After, line_length, &mut alignments);
+                }
+            }
+            NodeType::HashNode | NodeType::KeywordHashNode => {
+                let pad = match style.align_hash_values {
+                    HashAlignment::Preserve => None,
+                    HashAlignment::Key => Some(Pad::None),
+                    HashAlignment::Separator => Some(Pad::Before),
+                    HashAlignment::Table => Some(Pad::After),
+                };
+                if let Some(pad) = pad {
+                    let entries: Vec<_> = node
+                        .children
+                        .iter()
+                        .map(|child| pair(child, source, literals))
+                        .collect();
+                    for run in runs(&entries) {
+                        align(run, source, pad, line_length, &mut alignments);
+                    }
+                }
+            }
+            _ => {}
+        }
+        stack.extend(node.children.iter());
+    }
+    alignments
+}
+
+/// Add `alignments` to `literals`, skipping those inside a rewritten literal
+pub(super) fn merge(literals: &mut Vec<Literal>, alignments: Vec<Literal>) {
+    let overlaps = |a: &Literal| {
+        literals
+            .iter()
+            .any(|literal| literal.start < a.end && a.start < literal.end)
+    };
+    let alignments: Vec<_> = alignments.into_iter().filter(|a| !overlaps(a)).collect();
+    literals.extend(alignments);
+    literals.sort_by_key(|literal| literal.start);
+}
+
+/// `name = value` on one line, as the text before the value
+fn assignment<'a>(node: &Node, source: &'a str) -> Option<Entry<'a>> {
+    if !matches!(
+        node.node_type,
+        NodeType::LocalVariableWriteNode
+            | NodeType::InstanceVariableWriteNode
+            | NodeType::ClassVariableWriteNode
+            | NodeType::GlobalVariableWriteNode
+            | NodeType::ConstantWriteNode
+            | NodeType::ConstantPathWriteNode
+    ) || node.location.start_line != node.location.end_line
+    {
+        return None;
+    }
+    let start = node.location.start_offset;
+    let end = node.children.last()?.location.start_offset;
+    let name = source
+        .get(start..end)?
+        .trim_end()
+        .strip_suffix('=')?
+        .trim_end();
+    Some(Entry {
+        start,
+        end,
+        line: node.location.start_line,
+        column: node.location.start_column,
+        width: line_width(source, start),
+        name,
+        separator: " = ",
+    })
+}
+
+/// `key: value` or `key => value` on a line of its own, as the text before the value
+fn pair<'a>(node: &Node, source: &'a str, literals: &[Literal]) -> Option<Entry<'a>> {
+    let [key, value] = node.children.as_slice() else {
+        return None;
+    };
+    if !matches!(node.node_type, NodeType::AssocNode)
+        || matches!(value.node_type, NodeType::ImplicitNode)
+        || node.location.start_line != node.location.end_line
+    {
+        return None;
+    }
+    let (start, end) = (key.location.start_offset, value.location.start_offset);
+    if literals
+        .iter()
+        .any(|literal| literal.start < end && start < literal.end)
+    {
+        return None;
+    }
+    if !source[line_start(source, start)..start].trim().is_empty() {
+        return None;
+    }
+
+    let name = source.get(start..key.location.end_offset)?;
+    let gap = source.get(key.location.end_offset..end)?;
+    let separator = if gap.trim().is_empty() && name.ends_with(':') {
+        " "
+    } else if gap.trim() == "=>" {
+        " => "
+    } else {
+        return None;
+    };
+    Some(Entry {
+        start,
+        end,
+        line: node.location.start_line,
+        column: key.location.start_column,
+        width: line_width(source, start),
+        name,
+        separator,
+    })
+}
+
+/// Runs of two or more entries with the same separator on consecutive lines that
+/// start in the same column
+fn runs<'e, 'a>(entries: &'e [Option<Entry<'a>>]) -> Vec<&'e [Option<Entry<'a>>]> {
+    let continues = |prev: &Option<Entry>, next: &Option<Entry>| match (prev, next) {
+        (Some(prev), Some(next)) => {
+            next.line == prev.line + 1
+                && next.column == prev.column
+                && next.separator == prev.separator
+        }
+        _ => false,
+    };
+
+    let mut runs = Vec::new();
+    let mut run_start = 0;
+    for i in 1..=entries.len() {
+        if i < entries.len() && continues(&entries[i - 1], &entries[i]) {
+            continue;
+        }
+        if entries[run_start].is_some() && i - run_start > 1 {
+            runs.push(&entries[run_start..i]);
+        }
+        run_start = i;
+    }
+    runs
+}
+
+/// Push a replacement for each entry of `run` whose text changes, unless a line
+/// of the run would become wider than `line_length`
+fn align(
+    run: &[Option<Entry>],
+    source: &str,
+    pad: Pad,
+    line_length: usize,
+    alignments: &mut Vec<Literal>,
+) {
+    let entries: Vec<&Entry> = run.iter().flatten().collect();
+    let longest = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut replacements = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let padding = " ".repeat(longest - entry.name.chars().count());
+        let text = match pad {
+            Pad::None => format!("{}{}", entry.name, entry.separator),
+            Pad::After => format!("{}{}{}", entry.name, padding, entry.separator),
+            Pad::Before => format!("{}{}{}", padding, entry.name, entry.separator),
+        };
+        let old = &source[entry.start..entry.end];
+        if entry.width - old.chars().count() + text.chars().count() > line_length {
+            return;
+        }
+        if text != old {
+            replacements.push(Literal {
+                start: entry.start,
+                end: entry.end,
+                open: String::new(),
+                parts: vec![text],
+                close: String::new(),
+                layout: Layout::Text,
+            });
+        }
+    }
+    alignments.extend(replacements);
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Width in characters of the source line containing `offset`
+fn line_width(source: &str, offset: usize) -> usize {
+    let start = line_start(source, offset);
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    source[start..end].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    /// A node covering `text`, the first match in `source` at or after `from`
+    fn span(source: &str, node_type: NodeType, text: &str, from: usize) -> Node {
+        let start = from + source[from..].find(text).unwrap();
+        let end = start + text.len();
+        let line = source[..start].matches('\n').count() + 1;
+        let column = start - line_start(source, start);
+        let end_line = line + text.matches('\n').count();
+        Node::new(
+            node_type,
+            Location::new(line, column, end_line, column + text.len(), start, end),
+        )
+    }
+
+    /// Apply the replacements for `style` to `source`
+    fn aligned(source: &str, ast: &Node, style: &StyleConfig, line_length: usize) -> String {
+        let mut literals = Vec::new();
+        merge(
+            &mut literals,
+            collect_alignments(ast, source, style, line_length, &[]),
+        );
+        let mut text = String::new();
+        let mut pos = 0;
+        for literal in &literals {
+            text.push_str(&source[pos..literal.start]);
+            text.push_str(&literal.render(0, "", "", usize::MAX));
+            pos = literal.end;
+        }
+        text.push_str(&source[pos..]);
+        text
+    }
+
+    fn assignments(source: &str) -> Node {
+        let mut statements = Vec::new();
+        let mut from = 0;
+        for line in source.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                from += line.len() + 1;
+                continue;
+            };
+            let node_type = if name.starts_with(char::is_uppercase) {
+                NodeType::ConstantWriteNode
+            } else {
+                NodeType::LocalVariableWriteNode
+            };
+            let value = span(
+                source,
+                NodeType::IntegerNode,
+                value.trim(),
+                from + name.len(),
+            );
+            statements.push(span(source, node_type, line, from).with_children(vec![value]));
+            from += line.len() + 1;
+        }
+        span(source, NodeType::StatementsNode, source.trim_end(), 0).with_children(statements)
+    }
+
+    fn hash(source: &str) -> Node {
+        let mut pairs = Vec::new();
+        let mut from = source.find('\n').unwrap() + 1;
+        for line in source.lines().skip(1) {
+            let text = line.trim().trim_end_matches(',');
+            if let Some((key, value)) = text.split_once("=>").or_else(|| {
+                text.split_once(": ")
+                    .map(|(k, v)| (&text[..k.len() + 1], v))
+            }) {
+                let key = span(source, NodeType::SymbolNode, key.trim(), from);
+                let value = span(
+                    source,
+                    NodeType::IntegerNode,
+                    value.trim(),
+                    key.location.end_offset,
+                );
+                pairs.push(
+                    span(source, NodeType::AssocNode, text, from).with_children(vec![key, value]),
+                );
+            }
+            from += line.len() + 1;
+        }
+        span(source, NodeType::HashNode, source.trim_end(), 0).with_children(pairs)
+    }
+
+    fn style(align_assignments: bool, align_hash_values: HashAlignment) -> StyleConfig {
+        StyleConfig {
+            align_assignments,
+            align_hash_values,
+            ..StyleConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_aligns_runs_of_assignments() {
+        let source = "a = 1\nlonger   = 2\nC = 3\n\nsolo = 4\n";
+        let ast = assignments(source);
+        let on = style(true, HashAlignment::Preserve);
+
+        assert_eq!(
+            aligned(source, &ast, &on, 100),
+            "a      = 1\nlonger = 2\nC      = 3\n\nsolo = 4\n"
+        );
+        assert_eq!(
+            aligned(source, &ast, &style(false, HashAlignment::Preserve), 100),
+            source
+        );
+        assert_eq!(aligned(source, &ast, &on, 9), source);
+    }
+
+    #[test]
+    fn test_hash_alignment_styles() {
+        let source = "{\n  a: 1,\n  bbb:  2,\n  :c => 3,\n  :dd=>4\n}";
+        let ast = hash(source);
+        let with = |alignment| aligned(source, &ast, &style(false, alignment), 100);
+
+        assert_eq!(with(HashAlignment::Preserve), source);
+        assert_eq!(
+            with(HashAlignment::Key),
+            "{\n  a: 1,\n  bbb: 2,\n  :c => 3,\n  :dd => 4\n}"
+        );
+        assert_eq!(
+            with(HashAlignment::Separator),
+            "{\n    a: 1,\n  bbb: 2,\n   :c => 3,\n  :dd => 4\n}"
+        );
+        assert_eq!(
+            with(HashAlignment::Table),
+            "{\n  a:   1,\n  bbb: 2,\n  :c  => 3,\n  :dd => 4\n}"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+/// Methods whose block computes the value the call returns, written with braces
+/// under `block_delimiters: semantic`
+const FUNCTIONAL_METHODS: &[&str] = &[
+    "all?",
+    "any?",
+    "collect",
+    "count",
+    "detect",
+    "drop_while",
+    "each_with_object",
+    "filter",
+    "filter_map",
+    "find",
+    "find_all",
+    "find_index",
+    "flat_map",
+    "group_by",
+    "inject",
+    "let",
+    "let!",
+    "map",
+    "max_by",
+    "min_by",
+    "none?",
+    "one?",
+    "partition",
+    "reduce",
+    "reject",
+    "select",
+    "sort_by",
+    "subject",
+    "sum",
+    "take_while",
+    "to_h",
+    "transform_keys",
+    "transform_values",
+    "uniq",
+];
+
+/// Whether a block passed to `name` is used for its value rather than its side effects
+pub(super) fn is_functional(name: &str) -> bool {
+    FUNCTIONAL_METHODS.contains(&name)
+}
+
+/// A block on one line: `{ |x| body }`, `{ body }`, `{ |x| }` or `{}`
+pub(super) fn inline(parameters: Option<&str>, body: Option<&str>) -> String {
+    match (parameters, body) {
+        (Some(parameters), Some(body)) => format!("{{ {} {} }}", parameters, body),
+        (None, Some(body)) => format!("{{ {} }}", body),
+        (Some(parameters), None) => format!("{{ {} }}", parameters),
+        (None, None) => "{}".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_functional_methods_are_sorted() {
+        assert!(FUNCTIONAL_METHODS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(is_functional("map"));
+        assert!(!is_functional("each"));
+    }
+
+    #[test]
+    fn test_inline_block() {
+        assert_eq!(inline(Some("|x|"), Some("x * 2")), "{ |x| x * 2 }");
+        assert_eq!(inline(None, Some("_1.name")), "{ _1.name }");
+        assert_eq!(inline(Some("|_|"), None), "{ |_| }");
+        assert_eq!(inline(None, None), "{}");
+    }
+}
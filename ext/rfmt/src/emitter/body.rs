@@ -0,0 +1,265 @@
+use crate::ast::{Node, NodeType};
+use crate::config::{AccessModifierIndentation, BlankLineRule, BlankLinesConfig};
+
+/// Class-level macros that are grouped at the top of a body
+const MACROS: &[&str] = &[
+    "attr",
+    "attr_accessor",
+    "attr_reader",
+    "attr_writer",
+    "extend",
+    "include",
+    "prepend",
+];
+
+/// Visibility modifiers that change the methods defined after them
+const ACCESS_MODIFIERS: &[&str] = &["module_function", "private", "protected", "public"];
+
+/// Indentation of access modifiers and the sections they start in a body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ModifierLayout {
+    /// Modifiers and methods at the body level
+    Flat,
+    /// Modifiers at the level of `class`/`module`
+    Outdent,
+    /// Statements after a modifier one level deeper than it
+    Indent,
+}
+
+/// How to lay out the access modifiers among `statements`, the body of a class or
+/// module that starts at `definition_column`
+///
+/// `preserve` follows the first modifier in the source: outdented when it is not
+/// right of `class`/`module`, indented when the next statement is right of it.
+pub(super) fn modifier_layout(
+    statements: &[Node],
+    definition_column: usize,
+    style: &AccessModifierIndentation,
+) -> ModifierLayout {
+    match style {
+        AccessModifierIndentation::Outdent => ModifierLayout::Outdent,
+        AccessModifierIndentation::Indent => ModifierLayout::Indent,
+        AccessModifierIndentation::Preserve => {
+            let Some(i) = statements.iter().position(is_access_modifier) else {
+                return ModifierLayout::Flat;
+            };
+            let modifier = &statements[i].location;
+            match statements.get(i + 1) {
+                _ if modifier.start_column <= definition_column => ModifierLayout::Outdent,
+                Some(next)
+                    if next.location.start_line > modifier.end_line
+                        && next.location.start_column > modifier.start_column =>
+                {
+                    ModifierLayout::Indent
+                }
+                _ => ModifierLayout::Flat,
+            }
+        }
+    }
+}
+
+/// For each of `statements`, whether an access modifier section is open after it
+///
+/// `private`, `protected` and `module_function` open a section; `public` closes it.
+pub(super) fn open_sections(statements: &[Node]) -> Vec<bool> {
+    let mut open = false;
+    statements
+        .iter()
+        .map(|statement| {
+            if is_access_modifier(statement) {
+                open = is_bare_call(statement) != Some("public");
+            }
+            open
+        })
+        .collect()
+}
+
+/// A receiverless call named `name`, such as `include Comparable`
+fn is_bare_call(node: &Node) -> Option<&str> {
+    if !matches!(node.node_type, NodeType::CallNode) || node.metadata.contains_key("call_operator")
+    {
+        return None;
+    }
+    node.metadata.get("name").map(String::as_str)
+}
+
+/// A bare `private`, `protected`, `public` or `module_function` line, without
+/// arguments
+pub(super) fn is_access_modifier(node: &Node) -> bool {
+    is_bare_call(node).is_some_and(|name| ACCESS_MODIFIERS.contains(&name))
+        && node.children.is_empty()
+}
+
+/// An `include`, `extend`, `prepend` or `attr_*` call
+fn is_macro(node: &Node) -> bool {
+    is_bare_call(node).is_some_and(|name| MACROS.contains(&name)) && !node.children.is_empty()
+}
+
+/// Whether a class, module or method body puts a blank line between `prev` and
+/// `next`, given whether the source has one
+///
+/// Access modifier rules win over the rules for methods and macros.
+pub(super) fn blank_line_between(
+    prev: &Node,
+    next: &Node,
+    in_source: bool,
+    config: &BlankLinesConfig,
+) -> bool {
+    if is_access_modifier(prev) || is_access_modifier(next) {
+        return match config.around_access_modifiers {
+            BlankLineRule::Preserve => in_source,
+            BlankLineRule::Always => true,
+            BlankLineRule::Never => false,
+        };
+    }
+
+    let is_def = |node: &Node| matches!(node.node_type, NodeType::DefNode);
+    if config.between_methods && is_def(prev) && is_def(next) {
+        return true;
+    }
+    if config.after_macros && is_macro(prev) && !is_macro(next) {
+        return true;
+    }
+    in_source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn node(node_type: NodeType, line: usize) -> Node {
+        Node::new(node_type, Location::new(line, 2, line, 10, 0, 0))
+    }
+
+    fn call(name: &str, line: usize, arguments: usize) -> Node {
+        let mut call = node(NodeType::CallNode, line);
+        call.metadata.insert("name".to_string(), name.to_string());
+        call.with_children(
+            (0..arguments)
+                .map(|_| node(NodeType::ConstantReadNode, line))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_recognizes_access_modifiers_and_macros() {
+        assert!(is_access_modifier(&call("private", 1, 0)));
+        assert!(!is_access_modifier(&call("private", 1, 1)));
+        assert!(is_macro(&call("attr_reader", 1, 1)));
+        assert!(!is_macro(&call("validates", 1, 1)));
+
+        let mut qualified = call("private", 1, 0);
+        qualified
+            .metadata
+            .insert("call_operator".to_string(), ".".to_string());
+        assert!(!is_access_modifier(&qualified));
+    }
+
+    #[test]
+    fn test_preserve_modifier_layout() {
+        let at = |column: usize, node: Node| {
+            let mut node = node;
+            node.location.start_column = column;
+            node
+        };
+        let style = AccessModifierIndentation::Preserve;
+
+        let outdented = vec![
+            at(2, node(NodeType::DefNode, 2)),
+            at(0, call("private", 4, 0)),
+        ];
+        let indented = vec![
+            at(2, call("private", 2, 0)),
+            at(4, node(NodeType::DefNode, 3)),
+        ];
+        let flat = vec![
+            at(2, call("private", 2, 0)),
+            at(2, node(NodeType::DefNode, 3)),
+        ];
+
+        assert_eq!(
+            modifier_layout(&outdented, 0, &style),
+            ModifierLayout::Outdent
+        );
+        assert_eq!(
+            modifier_layout(&indented, 0, &style),
+            ModifierLayout::Indent
+        );
+        assert_eq!(modifier_layout(&flat, 0, &style), ModifierLayout::Flat);
+        assert_eq!(
+            modifier_layout(&flat, 0, &AccessModifierIndentation::Indent),
+            ModifierLayout::Indent
+        );
+    }
+
+    #[test]
+    fn test_public_closes_the_modifier_section() {
+        let statements = vec![
+            node(NodeType::DefNode, 1),
+            call("private", 2, 0),
+            node(NodeType::DefNode, 3),
+            call("public", 4, 0),
+            node(NodeType::DefNode, 5),
+            call("protected", 6, 0),
+        ];
+
+        assert_eq!(
+            open_sections(&statements),
+            vec![false, true, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn test_blank_lines_between_methods_and_after_macros() {
+        let config = BlankLinesConfig {
+            between_methods: true,
+            after_macros: true,
+            ..BlankLinesConfig::default()
+        };
+        let def = node(NodeType::DefNode, 1);
+        let include = call("include", 1, 1);
+        let extend = call("extend", 2, 1);
+
+        assert!(blank_line_between(&def, &def, false, &config));
+        assert!(blank_line_between(&include, &def, false, &config));
+        assert!(!blank_line_between(&include, &extend, false, &config));
+        assert!(blank_line_between(&include, &extend, true, &config));
+        assert!(!blank_line_between(
+            &def,
+            &def,
+            false,
+            &BlankLinesConfig::default()
+        ));
+    }
+
+    #[test]
+    fn test_blank_lines_around_access_modifiers() {
+        let private = call("private", 2, 0);
+        let def = node(NodeType::DefNode, 3);
+        let rule = |rule: BlankLineRule| BlankLinesConfig {
+            between_methods: true,
+            around_access_modifiers: rule,
+            ..BlankLinesConfig::default()
+        };
+
+        assert!(blank_line_between(
+            &def,
+            &private,
+            false,
+            &rule(BlankLineRule::Always)
+        ));
+        assert!(!blank_line_between(
+            &private,
+            &def,
+            true,
+            &rule(BlankLineRule::Never)
+        ));
+        assert!(blank_line_between(
+            &private,
+            &def,
+            true,
+            &rule(BlankLineRule::Preserve)
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use super::literal::Source;
+use crate::ast::{Node, NodeType};
+
+/// A method chain such as `User.where(active: true).order(:name).limit(10)`,
+/// flattened from the nested CallNode receivers
+#[derive(Debug)]
+pub(super) struct Chain<'a> {
+    /// The innermost receiver (`User`)
+    pub root: &'a Node,
+    /// Calls in source order, starting with the one applied to `root`
+    pub links: Vec<ChainLink>,
+}
+
+/// One `.message(args)` call in a chain
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct ChainLink {
+    /// The link as written, with whitespace after the operator removed and its
+    /// literals rewritten: `.order(:name)`
+    pub text: String,
+    /// Source range between the previous link (or the root) and this link's operator,
+    /// where comments may appear
+    pub gap: (usize, usize),
+    /// Whether the source had a line break before this link (or after its operator)
+    pub line_break: bool,
+}
+
+impl Chain<'_> {
+    /// Whether the source already had a line break between two links
+    pub fn is_broken(&self) -> bool {
+        self.links.iter().any(|link| link.line_break)
+    }
+
+    /// Whether the source had a line break right after the root
+    pub fn root_stands_alone(&self) -> bool {
+        self.links.first().is_some_and(|link| link.line_break)
+    }
+}
+
+/// Flatten the receivers of `node` into a chain
+///
+/// `end` is where the outermost link stops, so a trailing block can be left out.
+/// Returns `None` when `node` is not a `.`/`&.` call, or when the root or a link
+/// spans several lines and has to be copied verbatim.
+pub(super) fn flatten<'a>(node: &'a Node, end: usize, rewritten: &Source) -> Option<Chain<'a>> {
+    let source = rewritten.text();
+    let mut calls = Vec::new();
+    let mut current = node;
+    while let Some(receiver) = chain_receiver(current) {
+        calls.push(current);
+        current = receiver;
+    }
+
+    if calls.is_empty() {
+        return None;
+    }
+
+    let root = current;
+    let root_text = source.get(root.location.start_offset..root.location.end_offset)?;
+    if root_text.contains('\n') {
+        return None;
+    }
+
+    let mut links = Vec::with_capacity(calls.len());
+    for (i, call) in calls.iter().enumerate().rev() {
+        let receiver_end = call.children[0].location.end_offset;
+        let link_end = if i == 0 {
+            end
+        } else {
+            call.location.end_offset
+        };
+        let operator = call.metadata.get("call_operator")?;
+
+        let operator_start = find_operator(source, receiver_end, operator)?;
+        let after_operator = source.get(operator_start + operator.len()..link_end)?;
+        let message = after_operator.trim_start();
+        if message.is_empty() || message.starts_with(['#', '\\']) || message.contains('\n') {
+            return None;
+        }
+        let message = rewritten.slice(link_end - message.len(), link_end)?;
+
+        let gap = (receiver_end, operator_start);
+        let line_break = source[gap.0..gap.1].contains('\n') || after_operator.contains('\n');
+        links.push(ChainLink {
+            text: format!("{}{}", operator, message),
+            gap,
+            line_break,
+        });
+    }
+
+    Some(Chain { root, links })
+}
+
+/// The receiver of a `.` or `&.` call, which is always its first child
+fn chain_receiver(node: &Node) -> Option<&Node> {
+    if !matches!(node.node_type, NodeType::CallNode) {
+        return None;
+    }
+
+    match node.metadata.get("call_operator").map(String::as_str) {
+        Some(".") | Some("&.") => node.children.first(),
+        _ => None,
+    }
+}
+
+/// Find `operator` after `from`, skipping whitespace, line continuations and comments
+fn find_operator(source: &str, from: usize, operator: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\n' => i += 1,
+            b'\\' if bytes.get(i + 1) == Some(&b'\n') => i += 2,
+            b'#' => i = source[i..].find('\n').map_or(bytes.len(), |n| i + n),
+            _ => return source[i..].starts_with(operator).then_some(i),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    /// Build `receiver<op>message...` where the call spans `start..end`
+    fn call(receiver: Node, operator: &str, end: usize) -> Node {
+        let start = receiver.location.start_offset;
+        let mut node = Node::new(NodeType::CallNode, Location::new(1, 0, 1, 0, start, end))
+            .with_children(vec![receiver]);
+        node.metadata
+            .insert("call_operator".to_string(), operator.to_string());
+        node
+    }
+
+    fn constant(start: usize, end: usize) -> Node {
+        Node::new(
+            NodeType::ConstantReadNode,
+            Location::new(1, 0, 1, 0, start, end),
+        )
+    }
+
+    #[test]
+    fn test_flatten_single_line_chain() {
+        let source = "User.where(a: 1)&.first";
+        let node = call(call(constant(0, 4), ".", 16), "&.", 23);
+
+        let chain = flatten(&node, 23, &Source::plain(source)).unwrap();
+
+        assert_eq!(chain.root.location.end_offset, 4);
+        assert_eq!(
+            chain.links,
+            vec![
+                ChainLink {
+                    text: ".where(a: 1)".to_string(),
+                    gap: (4, 4),
+                    line_break: false,
+                },
+                ChainLink {
+                    text: "&.first".to_string(),
+                    gap: (16, 16),
+                    line_break: false,
+                },
+            ]
+        );
+        assert!(!chain.is_broken());
+    }
+
+    #[test]
+    fn test_flatten_broken_chain_with_comment() {
+        let source = "User\n  .where(a: 1) # only a\n  .first";
+        let node = call(call(constant(0, 4), ".", 19), ".", 37);
+
+        let chain = flatten(&node, 37, &Source::plain(source)).unwrap();
+
+        assert_eq!(chain.links[0].text, ".where(a: 1)");
+        assert_eq!(chain.links[1].text, ".first");
+        assert_eq!(chain.links[1].gap, (19, 31));
+        assert!(chain.is_broken());
+        assert!(chain.root_stands_alone());
+    }
+
+    #[test]
+    fn test_flatten_trailing_dot_chain() {
+        let source = "User.\n  where(a: 1)";
+        let node = call(constant(0, 4), ".", 19);
+
+        let chain = flatten(&node, 19, &Source::plain(source)).unwrap();
+
+        assert_eq!(chain.links[0].text, ".where(a: 1)");
+        assert!(chain.is_broken());
+    }
+
+    #[test]
+    fn test_flatten_rejects_multiline_link() {
+        let source = "User.where(\n  a: 1\n)";
+        let node = call(constant(0, 4), ".", 20);
+
+        assert!(flatten(&node, 20, &Source::plain(source)).is_none());
+    }
+
+    #[test]
+    fn test_flatten_rejects_plain_call() {
+        let node = Node::new(NodeType::CallNode, Location::new(1, 0, 1, 3, 0, 3));
+        assert!(flatten(&node, 3, &Source::plain("foo")).is_none());
+    }
+}
@@ -0,0 +1,304 @@
+use super::magic;
+use crate::ast::{Comment, CommentType};
+use crate::config::CommentsConfig;
+
+/// Apply the `comments` options to the comments of a file, in source order
+///
+/// With `reflow`, consecutive standalone prose lines in the same column are merged
+/// into one comment whose lines are joined by `\n`; they are refilled when written.
+pub(super) fn normalize(
+    mut comments: Vec<Comment>,
+    config: &CommentsConfig,
+    is_standalone: impl Fn(&Comment) -> bool,
+) -> Vec<Comment> {
+    comments.sort_by_key(|comment| comment.location.start_offset);
+
+    let mut normalized: Vec<Comment> = Vec::with_capacity(comments.len());
+    let mut in_paragraph = false;
+    for mut comment in comments {
+        let standalone = is_standalone(&comment);
+        if matches!(comment.comment_type, CommentType::Block) && config.convert_block_comments {
+            comment.text = line_comments(&comment.text);
+            comment.comment_type = CommentType::Line;
+        }
+        if config.strip_trailing_whitespace {
+            comment.text = comment
+                .text
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if config.space_after_hash && matches!(comment.comment_type, CommentType::Line) {
+            comment.text = comment
+                .text
+                .lines()
+                .map(space_after_hash)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let prose = config.reflow && standalone && is_paragraph(&comment.text);
+        if let Some(prev) = normalized.last_mut() {
+            if in_paragraph
+                && prose
+                && prev.location.start_column == comment.location.start_column
+                && prev.location.end_line + 1 == comment.location.start_line
+            {
+                prev.text.push('\n');
+                prev.text.push_str(&comment.text);
+                prev.location.end_line = comment.location.end_line;
+                prev.location.end_column = comment.location.end_column;
+                prev.location.end_offset = comment.location.end_offset;
+                continue;
+            }
+        }
+        in_paragraph = prose;
+        normalized.push(comment);
+    }
+    normalized
+}
+
+/// `=begin`/`=end` comment as `#` lines
+fn line_comments(text: &str) -> String {
+    text.lines()
+        .skip(1)
+        .take_while(|line| !line.starts_with("=end"))
+        .map(|line| match line {
+            "" => "#".to_string(),
+            line => format!("# {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `#comment` becomes `# comment`; shebangs, `##` and RDoc directives such as
+/// `#--` and `#:nodoc:` are left alone
+fn space_after_hash(line: &str) -> String {
+    match line.strip_prefix('#') {
+        Some(rest)
+            if !rest.is_empty() && !rest.starts_with([' ', '\t', '#', '!', ':', '-', '+']) =>
+        {
+            format!("# {}", rest)
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// Whether every line of a line comment is prose that can be refilled, rather than
+/// a YARD tag, a directive, a list item, an indented code block or a magic comment
+pub(super) fn is_paragraph(text: &str) -> bool {
+    text.lines().all(|line| {
+        let Some(words) = line.strip_prefix("# ") else {
+            return false;
+        };
+        let Some(first) = words.chars().next() else {
+            return false;
+        };
+        let list_item = words
+            .split_once(['.', ')'])
+            .is_some_and(|(number, _)| number.chars().all(|c| c.is_ascii_digit()));
+        !first.is_whitespace()
+            && !matches!(first, '@' | '-' | '*' | '+' | '|' | '>' | ':' | '#' | '`')
+            && !list_item
+            && !words.starts_with("rubocop:")
+            && magic::parse(line).is_none()
+    })
+}
+
+/// Refill the words of a prose comment into `# ` lines no wider than `width`
+///
+/// A word longer than the width gets a line of its own.
+pub(super) fn reflow(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::from("#");
+    for word in text
+        .lines()
+        .flat_map(|line| line.trim_start_matches('#').split_whitespace())
+    {
+        if current.len() > 1 && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::replace(&mut current, String::from("#")));
+        }
+        current.push(' ');
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines.join("\n")
+}
+
+/// A trailing comment in the output: where it starts, on which line, and the width
+/// and indentation of the code before it
+struct Trailing<'a> {
+    offset: usize,
+    line: usize,
+    width: usize,
+    indent: &'a str,
+}
+
+/// Pad the code before trailing comments so that comments on consecutive lines with
+/// the same indentation start in one column
+///
+/// `offsets` point at the space written before each `#`, in increasing order. Lines
+/// whose comment would start right of `line_length` are not aligned.
+pub(super) fn align_trailing(text: &str, offsets: &[usize], line_length: usize) -> String {
+    let mut entries = Vec::new();
+    let (mut line, mut pos) = (0, 0);
+    for &offset in offsets {
+        line += text[pos..offset].matches('\n').count();
+        pos = offset;
+        let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let code = &text[start..offset];
+        let width = code.chars().count();
+        if width < line_length {
+            let indent = &code[..code.len() - code.trim_start().len()];
+            entries.push(Trailing {
+                offset,
+                line,
+                width,
+                indent,
+            });
+        }
+    }
+
+    let mut pads = Vec::new();
+    let mut run_start = 0;
+    for i in 1..=entries.len() {
+        let continues = entries.get(i).is_some_and(|entry| {
+            let prev = &entries[i - 1];
+            entry.line == prev.line + 1 && entry.indent == prev.indent
+        });
+        if continues {
+            continue;
+        }
+        let run = &entries[run_start..i];
+        if run.len() > 1 {
+            let column = run.iter().map(|entry| entry.width).max().unwrap_or(0);
+            pads.extend(run.iter().map(|entry| (entry.offset, column - entry.width)));
+        }
+        run_start = i;
+    }
+
+    let mut aligned = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (offset, pad) in pads {
+        aligned.push_str(&text[pos..offset]);
+        aligned.push_str(&" ".repeat(pad));
+        pos = offset;
+    }
+    aligned.push_str(&text[pos..]);
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{CommentPosition, Location};
+
+    fn comment(line: usize, column: usize, text: &str) -> Comment {
+        Comment {
+            text: text.to_string(),
+            location: Location::new(line, column, line, column + text.len(), 0, 0),
+            comment_type: CommentType::Line,
+            position: CommentPosition::Leading,
+        }
+    }
+
+    fn texts(comments: Vec<Comment>, config: &CommentsConfig) -> Vec<String> {
+        normalize(comments, config, |_| true)
+            .into_iter()
+            .map(|comment| comment.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_space_after_hash_and_trailing_whitespace() {
+        let config = CommentsConfig {
+            space_after_hash: true,
+            strip_trailing_whitespace: true,
+            ..CommentsConfig::default()
+        };
+        let comments = vec![
+            comment(1, 0, "#!/usr/bin/env ruby"),
+            comment(2, 0, "#comment  "),
+            comment(3, 0, "#:nodoc:"),
+            comment(4, 0, "##"),
+            comment(5, 0, "#"),
+        ];
+        assert_eq!(
+            texts(comments, &config),
+            ["#!/usr/bin/env ruby", "# comment", "#:nodoc:", "##", "#"]
+        );
+    }
+
+    #[test]
+    fn test_convert_block_comments() {
+        let config = CommentsConfig {
+            convert_block_comments: true,
+            ..CommentsConfig::default()
+        };
+        let mut block = comment(1, 0, "=begin\nSome notes\n\nMore\n=end\n");
+        block.comment_type = CommentType::Block;
+        assert_eq!(texts(vec![block], &config), ["# Some notes\n#\n# More"]);
+    }
+
+    #[test]
+    fn test_merges_prose_paragraphs() {
+        let config = CommentsConfig {
+            reflow: true,
+            ..CommentsConfig::default()
+        };
+        let comments = vec![
+            comment(1, 2, "# Parses the"),
+            comment(2, 2, "# input."),
+            comment(3, 2, "#"),
+            comment(4, 2, "# @param input [String]"),
+            comment(5, 2, "# Returns nil"),
+            comment(6, 4, "# elsewhere"),
+        ];
+        assert_eq!(
+            texts(comments, &config),
+            [
+                "# Parses the\n# input.",
+                "#",
+                "# @param input [String]",
+                "# Returns nil",
+                "# elsewhere"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prose_detection() {
+        assert!(is_paragraph("# Returns the parsed value"));
+        assert!(!is_paragraph("# @return [String]"));
+        assert!(!is_paragraph("# rubocop:disable Metrics/AbcSize"));
+        assert!(!is_paragraph("#   indented_code(example)"));
+        assert!(!is_paragraph("# - list item"));
+        assert!(!is_paragraph("# 1. first step"));
+        assert!(!is_paragraph("# frozen_string_literal: true"));
+    }
+
+    #[test]
+    fn test_reflow() {
+        assert_eq!(
+            reflow("# one two\n# three four five", 16),
+            "# one two three\n# four five"
+        );
+        assert_eq!(
+            reflow("# https://example.com/a/very/long/url", 10),
+            "# https://example.com/a/very/long/url"
+        );
+    }
+
+    #[test]
+    fn test_align_trailing() {
+        let text = "A = 1 # one\nBB = 22 # two\n\nC = 3 # three\n  if x # nested\n";
+        let offsets: Vec<usize> = text.match_indices(" #").map(|(i, _)| i).collect();
+
+        assert_eq!(
+            align_trailing(text, &offsets, 100),
+            "A = 1   # one\nBB = 22 # two\n\nC = 3 # three\n  if x # nested\n"
+        );
+        assert_eq!(align_trailing(text, &offsets, 6), text);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::ast::{Node, NodeType};
+
+/// Whether `statement` keeps its meaning as the body of `def name = statement`
+///
+/// Modifiers, `and`/`or`, pattern matches and multiple assignment would bind to the
+/// whole definition or fail to parse, and `not` is only accepted there from Ruby 3.3.
+/// Jumps such as `return x`, `alias` and `undef` are statements, never accepted there.
+pub(super) fn is_endless_body(statement: &Node, source: &str) -> bool {
+    if matches!(
+        statement.node_type,
+        NodeType::IfNode
+            | NodeType::UnlessNode
+            | NodeType::WhileNode
+            | NodeType::UntilNode
+            | NodeType::RescueModifierNode
+            | NodeType::AndNode
+            | NodeType::OrNode
+            | NodeType::BeginNode
+            | NodeType::MultiWriteNode
+            | NodeType::MatchPredicateNode
+            | NodeType::MatchRequiredNode
+            | NodeType::ReturnNode
+            | NodeType::BreakNode
+            | NodeType::NextNode
+            | NodeType::RedoNode
+            | NodeType::RetryNode
+            | NodeType::AliasMethodNode
+            | NodeType::AliasGlobalVariableNode
+            | NodeType::UndefNode
+    ) {
+        return false;
+    }
+
+    !(matches!(statement.node_type, NodeType::CallNode)
+        && statement
+            .metadata
+            .get("name")
+            .is_some_and(|name| name == "!")
+        && source
+            .get(statement.location.start_offset..statement.location.end_offset)
+            .is_some_and(|text| text.starts_with("not")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn statement(node_type: NodeType, source: &str) -> Node {
+        Node::new(
+            node_type,
+            Location::new(1, 0, 1, source.len(), 0, source.len()),
+        )
+    }
+
+    fn negation(source: &str) -> Node {
+        let mut node = statement(NodeType::CallNode, source);
+        node.metadata.insert("name".to_string(), "!".to_string());
+        node
+    }
+
+    #[test]
+    fn test_accepts_plain_expressions() {
+        assert!(is_endless_body(
+            &statement(NodeType::CallNode, "name.upcase"),
+            "name.upcase"
+        ));
+        assert!(is_endless_body(&negation("!valid?"), "!valid?"));
+    }
+
+    #[test]
+    fn test_rejects_multiple_assignment() {
+        let source = "a, b = 1, 2";
+        assert!(!is_endless_body(
+            &statement(NodeType::MultiWriteNode, source),
+            source
+        ));
+    }
+
+    #[test]
+    fn test_rejects_match_predicate() {
+        let source = "x in Integer";
+        assert!(!is_endless_body(
+            &statement(NodeType::MatchPredicateNode, source),
+            source
+        ));
+    }
+
+    #[test]
+    fn test_rejects_match_required() {
+        let source = "x => y";
+        assert!(!is_endless_body(
+            &statement(NodeType::MatchRequiredNode, source),
+            source
+        ));
+    }
+
+    #[test]
+    fn test_rejects_statements() {
+        for (node_type, source) in [
+            (NodeType::ReturnNode, "return x"),
+            (NodeType::BreakNode, "break"),
+            (NodeType::NextNode, "next x"),
+            (NodeType::RedoNode, "redo"),
+            (NodeType::RetryNode, "retry"),
+            (NodeType::AliasMethodNode, "alias to_s name"),
+            (NodeType::AliasGlobalVariableNode, "alias $new $old"),
+            (NodeType::UndefNode, "undef to_s"),
+        ] {
+            assert!(!is_endless_body(&statement(node_type, source), source));
+        }
+    }
+
+    #[test]
+    fn test_rejects_not() {
+        assert!(!is_endless_body(&negation("not x"), "not x"));
+        assert!(!is_endless_body(&negation("not(x)"), "not(x)"));
+    }
+}
@@ -0,0 +1,267 @@
+use super::literal::Source;
+use crate::ast::{Node, NodeType};
+use std::collections::HashMap;
+
+/// Dependency methods called on the specification in a gemspec
+const DEPENDENCY_METHODS: &[&str] = &[
+    "add_dependency",
+    "add_development_dependency",
+    "add_runtime_dependency",
+];
+
+/// What kind of file is being formatted, chosen by its name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileKind {
+    #[default]
+    Ruby,
+    /// `Gemfile`, `gems.rb` or `*.gemspec`
+    Gemfile,
+}
+
+impl FileKind {
+    pub fn from_path(path: &str) -> Self {
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        if name == "Gemfile" || name == "gems.rb" || name.ends_with(".gemspec") {
+            FileKind::Gemfile
+        } else {
+            FileKind::Ruby
+        }
+    }
+}
+
+/// A gem declared again after `first`
+#[derive(Debug)]
+pub(super) struct Duplicate<'a> {
+    pub name: &'a str,
+    pub first: &'a Node,
+    pub repeated: &'a Node,
+}
+
+/// The arguments of a `gem 'name', ...` or `spec.add_dependency 'name', ...` call
+/// whose first argument is a plain string
+fn dependency_arguments(node: &Node) -> Option<&[Node]> {
+    if !matches!(node.node_type, NodeType::CallNode) {
+        return None;
+    }
+    let name = node.metadata.get("name")?.as_str();
+    let arguments = if node.metadata.contains_key("call_operator") {
+        if !DEPENDENCY_METHODS.contains(&name) {
+            return None;
+        }
+        node.children.get(1..)?
+    } else if name == "gem" {
+        node.children.as_slice()
+    } else {
+        return None;
+    };
+
+    let is_block = |child: &Node| {
+        matches!(
+            child.node_type,
+            NodeType::BlockNode | NodeType::BlockArgumentNode
+        )
+    };
+    if arguments.iter().any(is_block) {
+        return None;
+    }
+    matches!(arguments.first()?.node_type, NodeType::StringNode).then_some(arguments)
+}
+
+/// Name of the gem declared by a `gem` or `add_*dependency` call
+pub(super) fn gem_name(node: &Node) -> Option<&str> {
+    dependency_arguments(node)?
+        .first()?
+        .metadata
+        .get("content")
+        .map(String::as_str)
+}
+
+/// A gem declaration with its arguments separated by `, `, such as
+/// `gem 'rake', '~> 13.0', require: false`, if it fits on one line
+pub(super) fn declaration(node: &Node, source: &Source) -> Option<String> {
+    let arguments = dependency_arguments(node)?;
+
+    let mut parts = Vec::new();
+    for argument in arguments {
+        if matches!(argument.node_type, NodeType::KeywordHashNode) {
+            for element in &argument.children {
+                parts.push(pair(element, source)?);
+            }
+        } else {
+            parts.push(source.line(argument)?.into_owned());
+        }
+    }
+
+    let name = node.metadata.get("name")?;
+    let callee = match node.metadata.get("call_operator") {
+        Some(operator) => format!("{}{}{}", source.line(&node.children[0])?, operator, name),
+        None => name.clone(),
+    };
+    let parens = node.metadata.get("has_parens").is_some_and(|v| v == "true");
+    Some(if parens {
+        format!("{}({})", callee, parts.join(", "))
+    } else {
+        format!("{} {}", callee, parts.join(", "))
+    })
+}
+
+/// `key: value` or `key => value`
+fn pair(node: &Node, source: &Source) -> Option<String> {
+    match (&node.node_type, node.children.as_slice()) {
+        (NodeType::AssocNode, [key, value])
+            if !matches!(value.node_type, NodeType::ImplicitNode) =>
+        {
+            let key_text = source.line(key)?;
+            let value_text = source.line(value)?;
+            if key_text.ends_with(':') {
+                Some(format!("{} {}", key_text, value_text))
+            } else {
+                Some(format!("{} => {}", key_text, value_text))
+            }
+        }
+        _ => source.line(node).map(|text| text.into_owned()),
+    }
+}
+
+/// Gems declared more than once, in source order
+///
+/// Declarations under `if`, `unless` and `case` are not counted, since their
+/// branches may declare the same gem for different environments.
+pub(super) fn duplicates(ast: &Node) -> Vec<Duplicate<'_>> {
+    fn visit<'a>(
+        node: &'a Node,
+        seen: &mut HashMap<&'a str, &'a Node>,
+        found: &mut Vec<Duplicate<'a>>,
+    ) {
+        if let Some(name) = gem_name(node) {
+            match seen.get(name) {
+                Some(first) => found.push(Duplicate {
+                    name,
+                    first,
+                    repeated: node,
+                }),
+                None => {
+                    seen.insert(name, node);
+                }
+            }
+            return;
+        }
+        if matches!(
+            node.node_type,
+            NodeType::IfNode | NodeType::UnlessNode | NodeType::CaseNode
+        ) {
+            return;
+        }
+        for child in &node.children {
+            visit(child, seen, found);
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(ast, &mut HashMap::new(), &mut found);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    /// A node covering the first occurrence of `text` in the one-line `source`
+    fn span(source: &str, node_type: NodeType, text: &str) -> Node {
+        let start = source.find(text).unwrap();
+        let end = start + text.len();
+        Node::new(node_type, Location::new(1, start, 1, end, start, end))
+    }
+
+    fn string(source: &str, text: &str) -> Node {
+        let mut string = span(source, NodeType::StringNode, text);
+        string
+            .metadata
+            .insert("content".to_string(), text.trim_matches('\'').to_string());
+        string
+    }
+
+    fn gem(source: &str, arguments: Vec<Node>) -> Node {
+        let mut call = span(source, NodeType::CallNode, source);
+        call.metadata.insert("name".to_string(), "gem".to_string());
+        call.with_children(arguments)
+    }
+
+    #[test]
+    fn test_file_kind_from_path() {
+        assert_eq!(FileKind::from_path("Gemfile"), FileKind::Gemfile);
+        assert_eq!(FileKind::from_path("app/gems.rb"), FileKind::Gemfile);
+        assert_eq!(FileKind::from_path("rfmt.gemspec"), FileKind::Gemfile);
+        assert_eq!(FileKind::from_path("Gemfile.lock"), FileKind::Ruby);
+        assert_eq!(FileKind::from_path("lib/gemfile.rb"), FileKind::Ruby);
+        assert_eq!(FileKind::from_path(""), FileKind::Ruby);
+    }
+
+    #[test]
+    fn test_normalizes_argument_spacing() {
+        let text = "gem 'rake' ,'~> 13.0',require:false";
+        let pair = span(text, NodeType::AssocNode, "require:false").with_children(vec![
+            span(text, NodeType::SymbolNode, "require:"),
+            span(text, NodeType::FalseNode, "false"),
+        ]);
+        let hash = span(text, NodeType::KeywordHashNode, "require:false").with_children(vec![pair]);
+        let node = gem(
+            text,
+            vec![string(text, "'rake'"), string(text, "'~> 13.0'"), hash],
+        );
+
+        assert_eq!(gem_name(&node), Some("rake"));
+        assert_eq!(
+            declaration(&node, &Source::plain(text)).as_deref(),
+            Some("gem 'rake', '~> 13.0', require: false")
+        );
+    }
+
+    #[test]
+    fn test_rocket_keys_and_receivers() {
+        let text = "spec.add_dependency('thor',:platforms=>:ruby)";
+        let pair = span(text, NodeType::AssocNode, ":platforms=>:ruby").with_children(vec![
+            span(text, NodeType::SymbolNode, ":platforms"),
+            span(text, NodeType::SymbolNode, ":ruby"),
+        ]);
+        let hash =
+            span(text, NodeType::KeywordHashNode, ":platforms=>:ruby").with_children(vec![pair]);
+        let mut node = span(text, NodeType::CallNode, text).with_children(vec![
+            span(text, NodeType::LocalVariableReadNode, "spec"),
+            string(text, "'thor'"),
+            hash,
+        ]);
+        for (key, value) in [
+            ("name", "add_dependency"),
+            ("call_operator", "."),
+            ("has_parens", "true"),
+        ] {
+            node.metadata.insert(key.to_string(), value.to_string());
+        }
+
+        assert_eq!(gem_name(&node), Some("thor"));
+        assert_eq!(
+            declaration(&node, &Source::plain(text)).as_deref(),
+            Some("spec.add_dependency('thor', :platforms => :ruby)")
+        );
+    }
+
+    #[test]
+    fn test_duplicates_skip_conditionals() {
+        let text = "gem 'rails'";
+        let rails = || gem(text, vec![string(text, "'rails'")]);
+        let conditional = span(text, NodeType::IfNode, text).with_children(vec![rails()]);
+        let program = span(text, NodeType::ProgramNode, text).with_children(vec![
+            rails(),
+            conditional,
+            rails(),
+        ]);
+
+        let found = duplicates(&program);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "rails");
+        assert!(std::ptr::eq(found[0].first, &program.children[0]));
+        assert!(std::ptr::eq(found[0].repeated, &program.children[2]));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::ast::Node;
+
+/// How a heredoc body may be treated when its owner line moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum HeredocStyle {
+    /// `<<~`: common indentation is stripped, so the body can move with its owner
+    Squiggly,
+    /// `<<-` and `<<`: the body is literal and must stay byte-exact
+    Literal,
+}
+
+/// Byte ranges of a heredoc in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Heredoc {
+    pub style: HeredocStyle,
+    /// Offset of the opener (`<<~SQL`)
+    pub opener: usize,
+    /// Start of the line holding the opener (e.g. `sql = <<~SQL`)
+    pub owner_line_start: usize,
+    /// First byte of the body, on the line after the owner (or after the previous
+    /// heredoc's terminator when several start on the same line)
+    pub body_start: usize,
+    /// End of the terminator, excluding its newline
+    pub end: usize,
+}
+
+/// Collect every heredoc in the AST, ordered by body position
+///
+/// Heredoc nodes carry a `heredoc` metadata entry (`squiggly`, `dash` or `plain`) and
+/// span from the opener to the end of the terminator.
+pub(super) fn collect_heredocs(ast: &Node, source: &str) -> Vec<Heredoc> {
+    let mut openers = Vec::new();
+    let mut stack = vec![ast];
+    while let Some(node) = stack.pop() {
+        if let Some(style) = node.metadata.get("heredoc") {
+            let style = if style == "squiggly" {
+                HeredocStyle::Squiggly
+            } else {
+                HeredocStyle::Literal
+            };
+            openers.push((node.location.start_offset, node.location.end_offset, style));
+        }
+        stack.extend(node.children.iter());
+    }
+    openers.sort_unstable_by_key(|&(opener, _, _)| opener);
+
+    let mut heredocs: Vec<Heredoc> = Vec::with_capacity(openers.len());
+    for (opener, end, style) in openers {
+        let owner_line_start = source
+            .get(..opener)
+            .and_then(|before| before.rfind('\n'))
+            .map_or(0, |i| i + 1);
+
+        // A second heredoc on the same line starts after the previous terminator
+        let body_search_from = match heredocs.last() {
+            Some(prev) if prev.owner_line_start == owner_line_start => prev.end,
+            _ => opener,
+        };
+        let body_start = source
+            .get(body_search_from..)
+            .and_then(|rest| rest.find('\n'))
+            .map_or(end, |i| body_search_from + i + 1)
+            .min(end);
+
+        heredocs.push(Heredoc {
+            style,
+            opener,
+            owner_line_start,
+            body_start,
+            end,
+        });
+    }
+
+    // Moving a body that contains another heredoc would move that one too, which is
+    // only safe when both are squiggly; keep such outer bodies untouched instead
+    let owner_lines: Vec<usize> = heredocs.iter().map(|h| h.owner_line_start).collect();
+    for heredoc in &mut heredocs {
+        if owner_lines
+            .iter()
+            .any(|&line| line >= heredoc.body_start && line < heredoc.end)
+        {
+            heredoc.style = HeredocStyle::Literal;
+        }
+    }
+
+    heredocs.sort_by_key(|h| h.body_start);
+    heredocs
+}
+
+/// Move a `<<~` body and terminator from `old_indent` to `new_indent`
+///
+/// `text` runs from the first body line to the end of the terminator. Returns `None`
+/// when the lines cannot be shifted without changing the string's value, e.g. when
+/// tabs and spaces are mixed or a body line sits left of the owner.
+pub(super) fn reindent_squiggly(text: &str, old_indent: &str, new_indent: &str) -> Option<String> {
+    if old_indent == new_indent {
+        return Some(text.to_string());
+    }
+
+    let shift = |line: &str| -> Option<String> {
+        if let Some(extra) = new_indent.strip_prefix(old_indent) {
+            Some(format!("{}{}", extra, line))
+        } else if let Some(removed) = old_indent.strip_prefix(new_indent) {
+            line.strip_prefix(removed).map(str::to_string)
+        } else {
+            line.strip_prefix(old_indent)
+                .map(|rest| format!("{}{}", new_indent, rest))
+        }
+    };
+
+    let mut shifted = String::with_capacity(text.len() + new_indent.len() * 4);
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches('\n');
+        if content.is_empty() {
+            shifted.push_str(line);
+            continue;
+        }
+
+        match shift(line) {
+            Some(moved) => shifted.push_str(&moved),
+            None if is_blank(content) => shifted.push_str(line),
+            None => return None,
+        }
+    }
+
+    (squiggly_value(body_of(text)) == squiggly_value(body_of(&shifted))).then_some(shifted)
+}
+
+/// Leading spaces and tabs of `text`
+pub(super) fn leading_whitespace(text: &str) -> &str {
+    let end = text
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// The last complete line of `buffer`, ignoring its trailing newline
+pub(super) fn last_line(buffer: &str) -> &str {
+    let trimmed = buffer.strip_suffix('\n').unwrap_or(buffer);
+    trimmed.rfind('\n').map_or(trimmed, |i| &trimmed[i + 1..])
+}
+
+/// The body lines of `text`, without the terminator line
+fn body_of(text: &str) -> &str {
+    text.rsplit_once('\n').map_or("", |(body, _)| body)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.chars().all(|c| c == ' ' || c == '\t')
+}
+
+/// Width of a line's leading whitespace, with tabs advancing to the next multiple of 8
+fn indent_width(line: &str) -> usize {
+    let mut column = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => column += 1,
+            '\t' => column = (column / 8 + 1) * 8,
+            _ => break,
+        }
+    }
+    column
+}
+
+/// Remove up to `width` columns of leading whitespace, never splitting a tab
+fn strip_indent(line: &str, width: usize) -> &str {
+    let mut column = 0;
+    for (i, c) in line.char_indices() {
+        let next = match c {
+            ' ' => column + 1,
+            '\t' => (column / 8 + 1) * 8,
+            _ => return &line[i..],
+        };
+        if next > width {
+            return &line[i..];
+        }
+        column = next;
+    }
+    ""
+}
+
+/// The string a `<<~` body evaluates to, following Ruby's dedent rules: blank lines
+/// do not count towards the common indentation but are dedented like the others
+fn squiggly_value(body: &str) -> String {
+    let width = body
+        .split('\n')
+        .filter(|line| !is_blank(line))
+        .map(indent_width)
+        .min()
+        .unwrap_or(0);
+
+    body.split_inclusive('\n')
+        .map(|line| strip_indent(line, width))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Location, NodeType};
+    use std::collections::HashMap;
+
+    fn heredoc_node(style: &str, start: usize, end: usize) -> Node {
+        let mut node = Node::new(NodeType::StringNode, Location::new(1, 0, 1, 0, start, end));
+        node.metadata = HashMap::from([("heredoc".to_string(), style.to_string())]);
+        node
+    }
+
+    #[test]
+    fn test_collect_single_heredoc() {
+        let source = "x = <<~SQL\n  select\nSQL\n";
+        let ast = Node::new(NodeType::ProgramNode, Location::new(1, 0, 3, 3, 0, 23))
+            .with_children(vec![heredoc_node("squiggly", 4, 23)]);
+
+        let heredocs = collect_heredocs(&ast, source);
+
+        assert_eq!(
+            heredocs,
+            vec![Heredoc {
+                style: HeredocStyle::Squiggly,
+                opener: 4,
+                owner_line_start: 0,
+                body_start: 11,
+                end: 23,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_collect_heredocs_on_one_line() {
+        let source = "  f(<<~A, <<-B)\n  a\n  A\n  b\n  B\n";
+        let ast =
+            Node::new(NodeType::ProgramNode, Location::new(1, 0, 5, 3, 2, 31)).with_children(vec![
+                heredoc_node("squiggly", 4, 23),
+                heredoc_node("dash", 10, 31),
+            ]);
+
+        let heredocs = collect_heredocs(&ast, source);
+
+        assert_eq!(heredocs.len(), 2);
+        assert_eq!(heredocs[0].body_start, 16);
+        assert_eq!(heredocs[1].body_start, 24);
+        assert_eq!(heredocs[1].style, HeredocStyle::Literal);
+        assert!(heredocs.iter().all(|h| h.owner_line_start == 0));
+    }
+
+    #[test]
+    fn test_reindent_adds_indentation() {
+        let moved = reindent_squiggly("  select\n\n  from\nSQL", "", "    ").unwrap();
+        assert_eq!(moved, "      select\n\n      from\n    SQL");
+    }
+
+    #[test]
+    fn test_reindent_removes_indentation() {
+        let moved = reindent_squiggly("        a\n          b\n      A", "      ", "  ").unwrap();
+        assert_eq!(moved, "    a\n      b\n  A");
+    }
+
+    #[test]
+    fn test_reindent_refuses_body_left_of_owner() {
+        assert_eq!(reindent_squiggly("a\n  A", "  ", ""), None);
+    }
+
+    #[test]
+    fn test_reindent_refuses_value_change() {
+        // A tab after the added spaces no longer lines up with the space-indented line
+        assert_eq!(reindent_squiggly("\tx\n        y\nA", "", "  "), None);
+    }
+
+    #[test]
+    fn test_last_line_and_leading_whitespace() {
+        let buffer = "class Foo\n    x = <<~A\n";
+        assert_eq!(last_line(buffer), "    x = <<~A");
+        assert_eq!(leading_whitespace(last_line(buffer)), "    ");
+        assert_eq!(leading_whitespace("\t y"), "\t ");
+    }
+
+    #[test]
+    fn test_squiggly_value() {
+        assert_eq!(squiggly_value("    a\n      b\n  \n    c"), "a\n  b\n\nc");
+        assert_eq!(squiggly_value("\ta\n        b"), "a\nb");
+    }
+}
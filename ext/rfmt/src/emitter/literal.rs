@@ -0,0 +1,467 @@
+use super::numeric;
+use crate::ast::{Comment, Node, NodeType};
+use crate::config::{QuoteStyle, StyleConfig, WordArrays};
+use std::borrow::Cow;
+
+/// How the parts of a rewritten literal are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Layout {
+    /// `%w[a b]`: parts separated by spaces, filled across lines when too wide
+    Words,
+    /// `['a', 'b']`: one part per line when too wide
+    List,
+    /// `%q(text)` or a number: a single body kept as written
+    Text,
+}
+
+/// A literal rewritten while copying source: a percent literal with configured
+/// delimiters, a word array converted to or from `%w`, a normalized number, or the
+/// padded text before an aligned value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Literal {
+    pub start: usize,
+    pub end: usize,
+    /// `%w[`, `%q(` or `[`
+    pub open: String,
+    pub parts: Vec<String>,
+    pub close: String,
+    pub layout: Layout,
+}
+
+impl Literal {
+    /// Render the literal starting at `column`. When it does not fit in `line_length`,
+    /// parts are wrapped one `step` deeper than `indent`, the indentation of the line
+    /// the literal starts on.
+    pub fn render(&self, column: usize, indent: &str, step: &str, line_length: usize) -> String {
+        let flat = match self.layout {
+            Layout::Words => format!("{}{}{}", self.open, self.parts.join(" "), self.close),
+            Layout::List => format!("{}{}{}", self.open, self.parts.join(", "), self.close),
+            Layout::Text => return format!("{}{}{}", self.open, self.parts.concat(), self.close),
+        };
+        if self.parts.len() < 2 || column + flat.chars().count() <= line_length {
+            return flat;
+        }
+
+        let inner = format!("{}{}", indent, step);
+        let mut lines: Vec<String> = Vec::new();
+        match self.layout {
+            Layout::Words => {
+                let mut line = String::new();
+                for part in &self.parts {
+                    let width = inner.chars().count() + line.chars().count();
+                    if !line.is_empty() && width + 1 + part.chars().count() > line_length {
+                        lines.push(std::mem::take(&mut line));
+                    }
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(part);
+                }
+                lines.push(line);
+            }
+            _ => {
+                let last = self.parts.len() - 1;
+                for (i, part) in self.parts.iter().enumerate() {
+                    lines.push(if i < last {
+                        format!("{},", part)
+                    } else {
+                        part.clone()
+                    });
+                }
+            }
+        }
+
+        let mut text = self.open.clone();
+        for line in lines {
+            text.push('\n');
+            text.push_str(&inner);
+            text.push_str(&line);
+        }
+        text.push('\n');
+        text.push_str(indent);
+        text.push_str(&self.close);
+        text
+    }
+}
+
+/// Collect the literals to rewrite under `style`, ordered by position
+///
+/// Percent literals are rewritten when `percent_literal_delimiters` covers their type,
+/// word arrays when `word_arrays` asks for the other form, and numbers when
+/// `numeric_literals` changes them. Literals whose contents would clash with the new
+/// delimiters are left alone.
+pub(super) fn collect_literals(
+    ast: &Node,
+    source: &str,
+    style: &StyleConfig,
+    comments: &[Comment],
+) -> Vec<Literal> {
+    let mut literals = Vec::new();
+    let mut stack = vec![ast];
+    while let Some(node) = stack.pop() {
+        match literal(node, source, style, comments) {
+            Some(literal) => literals.push(literal),
+            None => stack.extend(node.children.iter()),
+        }
+    }
+    literals.sort_by_key(|literal| literal.start);
+    literals
+}
+
+fn literal(
+    node: &Node,
+    source: &str,
+    style: &StyleConfig,
+    comments: &[Comment],
+) -> Option<Literal> {
+    if matches!(node.node_type, NodeType::IntegerNode | NodeType::FloatNode) {
+        return numeric_literal(node, source, style);
+    }
+
+    let opening = node.metadata.get("opening")?;
+    let (start, end) = (node.location.start_offset, node.location.end_offset);
+    let text = source.get(start..end)?;
+
+    if opening == "[" {
+        let (open, close) = delimiters(style, "%w").unwrap_or(('[', ']'));
+        let has_comments = comments
+            .iter()
+            .any(|c| c.location.start_offset >= start && c.location.start_offset < end);
+        if !matches!(node.node_type, NodeType::ArrayNode)
+            || !matches!(style.word_arrays, WordArrays::Percent)
+            || node.children.is_empty()
+            || has_comments
+        {
+            return None;
+        }
+        let words = node
+            .children
+            .iter()
+            .map(|child| quoted_word(child, source))
+            .collect::<Option<Vec<_>>>()?;
+        return Some(Literal {
+            start,
+            end,
+            open: format!("%w{}", open),
+            parts: words.into_iter().map(str::to_string).collect(),
+            close: close.to_string(),
+            layout: Layout::Words,
+        });
+    }
+
+    let (kind, old_open) = percent_kind(opening)?;
+    let old_close = text.chars().last()?;
+    let body = text.get(opening.len()..text.len() - old_close.len_utf8())?;
+
+    let (layout, parts) = match node.node_type {
+        NodeType::ArrayNode if matches!(kind, "%w" | "%W" | "%i" | "%I") => {
+            let parts = node
+                .children
+                .iter()
+                .map(|child| {
+                    source
+                        .get(child.location.start_offset..child.location.end_offset)
+                        .map(str::to_string)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            (Layout::Words, parts)
+        }
+        NodeType::StringNode | NodeType::InterpolatedStringNode
+            if matches!(kind, "%" | "%q" | "%Q") =>
+        {
+            (Layout::Text, vec![body.to_string()])
+        }
+        _ => return None,
+    };
+
+    if kind == "%w"
+        && matches!(style.word_arrays, WordArrays::Brackets)
+        && !parts.is_empty()
+        && parts.iter().all(|part| is_simple_word(part))
+    {
+        let quote = match style.quotes {
+            QuoteStyle::Single => '\'',
+            _ => '"',
+        };
+        return Some(Literal {
+            start,
+            end,
+            open: "[".to_string(),
+            parts: parts
+                .iter()
+                .map(|part| format!("{}{}{}", quote, part, quote))
+                .collect(),
+            close: "]".to_string(),
+            layout: Layout::List,
+        });
+    }
+
+    let (open, close) = match delimiters(style, kind) {
+        Some(delimiters) => delimiters,
+        // `word_arrays: percent` also lays out the `%w` arrays already in the source
+        None if kind == "%w" && matches!(style.word_arrays, WordArrays::Percent) => {
+            (old_open, old_close)
+        }
+        None => return None,
+    };
+    if !can_redelimit(body, (old_open, old_close), (open, close)) {
+        return None;
+    }
+
+    Some(Literal {
+        start,
+        end,
+        open: format!("{}{}", kind, open),
+        parts,
+        close: close.to_string(),
+        layout,
+    })
+}
+
+/// An integer or float whose spelling changes under `numeric_literals`
+fn numeric_literal(node: &Node, source: &str, style: &StyleConfig) -> Option<Literal> {
+    let (start, end) = (node.location.start_offset, node.location.end_offset);
+    let text = source.get(start..end)?;
+    let normalized = numeric::normalize(text, &style.numeric_literals);
+    (normalized != text).then(|| Literal {
+        start,
+        end,
+        open: String::new(),
+        parts: vec![normalized],
+        close: String::new(),
+        layout: Layout::Text,
+    })
+}
+
+/// The source with rewritten literals applied, for code that rebuilds expressions
+/// from the text of their nodes instead of copying source ranges
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Source<'a> {
+    text: &'a str,
+    literals: &'a [Literal],
+}
+
+impl<'a> Source<'a> {
+    pub fn new(text: &'a str, literals: &'a [Literal]) -> Self {
+        Self { text, literals }
+    }
+
+    /// Source without rewritten literals
+    #[cfg(test)]
+    pub fn plain(text: &'a str) -> Self {
+        Self::new(text, &[])
+    }
+
+    /// The source as written
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Text of `node` with its literals rewritten, if it fits on one line
+    pub fn line(&self, node: &Node) -> Option<Cow<'a, str>> {
+        let text = self.slice(node.location.start_offset, node.location.end_offset)?;
+        (!text.contains('\n')).then_some(text)
+    }
+
+    /// `text[start..end]` with the literals inside it rewritten
+    pub fn slice(&self, start: usize, end: usize) -> Option<Cow<'a, str>> {
+        let text = self.text.get(start..end)?;
+        let first = self
+            .literals
+            .partition_point(|literal| literal.start < start);
+        let inside = self.literals[first..]
+            .iter()
+            .take_while(|literal| literal.end <= end);
+
+        let mut rewritten: Option<String> = None;
+        let mut pos = start;
+        for literal in inside {
+            let buffer = rewritten.get_or_insert_with(String::new);
+            buffer.push_str(&self.text[pos..literal.start]);
+            buffer.push_str(&literal.render(0, "", "", usize::MAX));
+            pos = literal.end;
+        }
+        Some(match rewritten {
+            Some(mut buffer) => {
+                buffer.push_str(&self.text[pos..end]);
+                Cow::Owned(buffer)
+            }
+            None => Cow::Borrowed(text),
+        })
+    }
+}
+
+/// Split a percent literal opening such as `%w[` into its type and delimiter
+fn percent_kind(opening: &str) -> Option<(&str, char)> {
+    if !opening.starts_with('%') {
+        return None;
+    }
+    let delimiter = opening.chars().last()?;
+    let kind = &opening[..opening.len() - delimiter.len_utf8()];
+    matches!(kind, "%" | "%q" | "%Q" | "%w" | "%W" | "%i" | "%I").then_some((kind, delimiter))
+}
+
+/// Configured delimiters for a percent literal type, falling back to `default`
+fn delimiters(style: &StyleConfig, kind: &str) -> Option<(char, char)> {
+    let delimiters = style
+        .percent_literal_delimiters
+        .get(kind)
+        .or_else(|| style.percent_literal_delimiters.get("default"))?;
+    let mut chars = delimiters.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(open), Some(close), None) => Some((open, close)),
+        _ => None,
+    }
+}
+
+/// Whether `body` means the same between `new` delimiters as between `old` ones: it
+/// must not contain the new delimiters, nor escape the old ones
+fn can_redelimit(body: &str, old: (char, char), new: (char, char)) -> bool {
+    if old == new {
+        return true;
+    }
+    let escapes_old =
+        body.contains(&format!("\\{}", old.0)) || body.contains(&format!("\\{}", old.1));
+    !escapes_old && !body.contains(new.0) && !body.contains(new.1)
+}
+
+/// The word inside a quoted string element (`'name'` or `"name"`) of a bracket array
+fn quoted_word<'a>(node: &Node, source: &'a str) -> Option<&'a str> {
+    if !matches!(node.node_type, NodeType::StringNode) {
+        return None;
+    }
+    let quote = node.metadata.get("opening")?;
+    if quote != "'" && quote != "\"" {
+        return None;
+    }
+    let text = source.get(node.location.start_offset..node.location.end_offset)?;
+    let word = text.get(1..text.len().checked_sub(1)?)?;
+    is_simple_word(word).then_some(word)
+}
+
+/// A word that reads the same in `%w[]` and in quotes: letters, digits, `_` and `-`
+fn is_simple_word(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn node(node_type: NodeType, start: usize, end: usize) -> Node {
+        Node::new(node_type, Location::new(1, start, 1, end, start, end))
+    }
+
+    fn with_opening(mut node: Node, opening: &str) -> Node {
+        node.metadata
+            .insert("opening".to_string(), opening.to_string());
+        node
+    }
+
+    fn style(delimiters: &[(&str, &str)], word_arrays: WordArrays) -> StyleConfig {
+        let mut style = StyleConfig::default();
+        for (kind, pair) in delimiters {
+            style
+                .percent_literal_delimiters
+                .insert(kind.to_string(), pair.to_string());
+        }
+        style.word_arrays = word_arrays;
+        style
+    }
+
+    /// `%w(a  bc)`
+    fn word_array() -> (Node, &'static str) {
+        let source = "%w(a  bc)";
+        let array = with_opening(node(NodeType::ArrayNode, 0, 9), "%w(").with_children(vec![
+            node(NodeType::StringNode, 3, 4),
+            node(NodeType::StringNode, 6, 8),
+        ]);
+        (array, source)
+    }
+
+    #[test]
+    fn test_percent_array_delimiters() {
+        let (array, source) = word_array();
+        let style = style(&[("%w", "[]")], WordArrays::Preserve);
+
+        let literals = collect_literals(&array, source, &style, &[]);
+
+        assert_eq!(literals.len(), 1);
+        assert_eq!(literals[0].render(0, "", "  ", 100), "%w[a bc]");
+    }
+
+    #[test]
+    fn test_default_delimiters_and_unconfigured_types() {
+        let source = "%q[it's]";
+        let string = with_opening(node(NodeType::StringNode, 0, 8), "%q[");
+
+        let configured = collect_literals(
+            &string,
+            source,
+            &style(&[("default", "()")], WordArrays::Preserve),
+            &[],
+        );
+        assert_eq!(configured[0].render(0, "", "  ", 100), "%q(it's)");
+        assert!(collect_literals(&string, source, &StyleConfig::default(), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_clashing_delimiters_are_kept() {
+        let source = "%q[a (b]";
+        let string = with_opening(node(NodeType::StringNode, 0, 8), "%q[");
+        let style = style(&[("%q", "()")], WordArrays::Preserve);
+
+        assert!(collect_literals(&string, source, &style, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_word_arrays_to_brackets() {
+        let (array, source) = word_array();
+        let mut style = style(&[], WordArrays::Brackets);
+        style.quotes = QuoteStyle::Single;
+
+        let literals = collect_literals(&array, source, &style, &[]);
+
+        assert_eq!(literals[0].render(0, "", "  ", 100), "['a', 'bc']");
+    }
+
+    #[test]
+    fn test_bracket_arrays_to_words() {
+        // ['a', "b-c"]
+        let source = "['a', \"b-c\"]";
+        let array = with_opening(node(NodeType::ArrayNode, 0, 12), "[").with_children(vec![
+            with_opening(node(NodeType::StringNode, 1, 4), "'"),
+            with_opening(node(NodeType::StringNode, 6, 11), "\""),
+        ]);
+
+        let literals = collect_literals(&array, source, &style(&[], WordArrays::Percent), &[]);
+        assert_eq!(literals[0].render(0, "", "  ", 100), "%w[a b-c]");
+
+        let preserved = collect_literals(&array, source, &StyleConfig::default(), &[]);
+        assert!(preserved.is_empty());
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        let literal = Literal {
+            start: 0,
+            end: 0,
+            open: "%w[".to_string(),
+            parts: ["alpha", "beta", "gamma", "delta"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            close: "]".to_string(),
+            layout: Layout::Words,
+        };
+
+        assert_eq!(
+            literal.render(30, "  ", "  ", 18),
+            "%w[\n    alpha beta\n    gamma delta\n  ]"
+        );
+    }
+}
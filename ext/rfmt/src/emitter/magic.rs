@@ -0,0 +1,139 @@
+/// Magic comment keys, in the order they are written at the top of a file
+const KEYS: &[&str] = &[
+    "encoding",
+    "frozen_string_literal",
+    "shareable_constant_value",
+    "warn_indent",
+    "typed",
+];
+
+/// A `# key: value` comment that changes how Ruby (or Sorbet) reads the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MagicComment {
+    key: &'static str,
+    value: String,
+}
+
+impl MagicComment {
+    pub fn frozen_string_literal() -> Self {
+        Self {
+            key: "frozen_string_literal",
+            value: "true".to_string(),
+        }
+    }
+
+    pub fn is_frozen_string_literal(&self) -> bool {
+        self.key == "frozen_string_literal"
+    }
+
+    pub fn is_encoding(&self) -> bool {
+        self.key == "encoding"
+    }
+
+    /// Position in the canonical order
+    pub fn rank(&self) -> usize {
+        KEYS.iter()
+            .position(|&key| key == self.key)
+            .unwrap_or(KEYS.len())
+    }
+
+    pub fn text(&self) -> String {
+        format!("# {}: {}", self.key, self.value)
+    }
+}
+
+/// The magic comments in a comment such as `# Frozen-String-Literal: TRUE` or
+/// `# -*- coding: utf-8; frozen_string_literal: true -*-`
+///
+/// Keys are spelled in lowercase with underscores, `coding` becomes `encoding`,
+/// and values other than the encoding name are lowercased. Returns `None` unless
+/// every entry is a known magic comment.
+pub(super) fn parse(text: &str) -> Option<Vec<MagicComment>> {
+    let body = text.strip_prefix('#')?.trim();
+    let entries = match body
+        .strip_prefix("-*-")
+        .and_then(|body| body.strip_suffix("-*-"))
+    {
+        Some(emacs) => emacs.split(';').filter(|e| !e.trim().is_empty()).collect(),
+        None => vec![body],
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let (key, value) = entry.split_once(':')?;
+            let key = key.trim().to_ascii_lowercase().replace('-', "_");
+            let key = if key == "coding" { "encoding" } else { &key };
+            let key = *KEYS.iter().find(|&&known| known == key)?;
+
+            let value = value.trim();
+            let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+            if value.is_empty() || !value.chars().all(is_word) {
+                return None;
+            }
+            let value = if key == "encoding" {
+                value.to_string()
+            } else {
+                value.to_ascii_lowercase()
+            };
+            Some(MagicComment { key, value })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(comment: &str) -> Option<Vec<String>> {
+        parse(comment).map(|magic| magic.iter().map(MagicComment::text).collect())
+    }
+
+    #[test]
+    fn test_normalizes_spelling_and_case() {
+        assert_eq!(
+            texts("# Frozen-String-Literal: TRUE"),
+            Some(vec!["# frozen_string_literal: true".to_string()])
+        );
+        assert_eq!(
+            texts("#coding:Shift_JIS"),
+            Some(vec!["# encoding: Shift_JIS".to_string()])
+        );
+        assert_eq!(
+            texts("# typed: Strict"),
+            Some(vec!["# typed: strict".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_splits_emacs_style_comments() {
+        assert_eq!(
+            texts("# -*- coding: utf-8; frozen_string_literal: true -*-"),
+            Some(vec![
+                "# encoding: utf-8".to_string(),
+                "# frozen_string_literal: true".to_string()
+            ])
+        );
+        assert_eq!(texts("# -*- mode: ruby; coding: utf-8 -*-"), None);
+    }
+
+    #[test]
+    fn test_ignores_ordinary_comments() {
+        assert_eq!(texts("# Encoding: handled by the reader below"), None);
+        assert_eq!(texts("# TODO: remove"), None);
+        assert_eq!(texts("#!/usr/bin/env ruby"), None);
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let mut magic = parse("# -*- typed: true; warn_indent: true; encoding: ascii -*-").unwrap();
+        magic.push(MagicComment::frozen_string_literal());
+        magic.sort_by_key(MagicComment::rank);
+
+        let keys: Vec<_> = magic.iter().map(|m| m.key).collect();
+        assert_eq!(
+            keys,
+            ["encoding", "frozen_string_literal", "warn_indent", "typed"]
+        );
+    }
+}
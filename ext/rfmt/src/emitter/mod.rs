@@ -1,6 +1,31 @@
-use crate::ast::{Comment, Node, NodeType};
-use crate::config::{Config, IndentStyle};
+mod align;
+mod block;
+mod body;
+mod chain;
+mod comment;
+mod endless;
+mod gemfile;
+mod heredoc;
+mod literal;
+mod magic;
+mod numeric;
+mod operator;
+mod parameters;
+mod pattern;
+mod requires;
+
+use crate::ast::{Comment, CommentType, Location, Node, NodeType};
+use crate::config::{BlockDelimiters, CaseWhen, Config, IndentStyle, MethodStyle};
 use crate::error::Result;
+use crate::policy::Deadline;
+use body::ModifierLayout;
+use chain::Chain;
+pub use gemfile::FileKind;
+use heredoc::{last_line, leading_whitespace, Heredoc, HeredocStyle};
+use literal::{Literal, Source};
+use magic::MagicComment;
+use operator::Expression;
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::Write;
 
@@ -11,6 +36,33 @@ enum BlockStyle {
     Braces, // { ... }
 }
 
+/// Order of a statement within a run of lines that are sorted together
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum RunKey<'a> {
+    /// `require` lines, by group and path
+    Require(requires::Group, &'a str),
+    /// Gem declarations, by method and name
+    Gem(&'a str, String),
+}
+
+impl RunKey<'_> {
+    /// Whether statements with these keys can be sorted in the same run
+    fn same_run(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RunKey::Require(..), RunKey::Require(..)) => true,
+            (RunKey::Gem(method, _), RunKey::Gem(other, _)) => method == other,
+            _ => false,
+        }
+    }
+}
+
+/// A problem found in the source while formatting it
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Location,
+}
+
 /// Code emitter that converts AST back to Ruby source code
 pub struct Emitter {
     config: Config,
@@ -23,6 +75,21 @@ pub struct Emitter {
     /// Index of comment indices by start line for O(log n) lookup
     /// Key: start_line, Value: Vec of comment indices that start on that line
     comments_by_line: BTreeMap<usize, Vec<usize>>,
+    /// Time budget checked while walking nodes
+    deadline: Deadline,
+    /// Heredocs in the source, ordered by body position
+    heredocs: Vec<Heredoc>,
+    /// Literals rewritten while copying source, ordered by position
+    literals: Vec<Literal>,
+    /// Kind of file being formatted, which enables the Gemfile rules
+    file_kind: FileKind,
+    /// Problems found during the last `emit`
+    diagnostics: Vec<Diagnostic>,
+    /// Buffer offsets of the space before each trailing comment, in order
+    trailing_comments: Vec<usize>,
+    /// Source offsets of the comments `write_node_source` is copying, and the buffer
+    /// offset each one was copied to
+    copied_comments: Vec<(usize, Option<usize>)>,
 }
 
 impl Emitter {
@@ -35,6 +102,13 @@ impl Emitter {
             emitted_comment_indices: HashSet::new(),
             indent_cache: Vec::new(),
             comments_by_line: BTreeMap::new(),
+            deadline: Deadline::unlimited(),
+            heredocs: Vec::new(),
+            literals: Vec::new(),
+            file_kind: FileKind::Ruby,
+            diagnostics: Vec::new(),
+            trailing_comments: Vec::new(),
+            copied_comments: Vec::new(),
         }
     }
 
@@ -48,18 +122,76 @@ impl Emitter {
             emitted_comment_indices: HashSet::new(),
             indent_cache: Vec::new(),
             comments_by_line: BTreeMap::new(),
+            deadline: Deadline::unlimited(),
+            heredocs: Vec::new(),
+            literals: Vec::new(),
+            file_kind: FileKind::Ruby,
+            diagnostics: Vec::new(),
+            trailing_comments: Vec::new(),
+            copied_comments: Vec::new(),
         }
     }
 
+    /// Abort emission with `RfmtError::Timeout` once the deadline passes
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Format the source as the given kind of file
+    pub fn with_file_kind(mut self, file_kind: FileKind) -> Self {
+        self.file_kind = file_kind;
+        self
+    }
+
+    /// Problems found during the last `emit`, such as duplicate gems
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     /// Emit Ruby source code from an AST
     pub fn emit(&mut self, ast: &Node) -> Result<String> {
         self.buffer.clear();
         self.emitted_comment_indices.clear();
         self.comments_by_line.clear();
+        self.diagnostics.clear();
+        self.trailing_comments.clear();
 
         self.collect_comments(ast);
+        let comments = std::mem::take(&mut self.all_comments);
+        self.all_comments = comment::normalize(comments, &self.config.formatting.comments, |c| {
+            self.is_standalone_comment(c)
+        });
         self.build_comment_index();
+        self.heredocs = heredoc::collect_heredocs(ast, &self.source);
+        self.literals = literal::collect_literals(
+            ast,
+            &self.source,
+            &self.config.formatting.style,
+            &self.all_comments,
+        );
+        let alignments = align::collect_alignments(
+            ast,
+            &self.source,
+            &self.config.formatting.style,
+            self.config.formatting.line_length,
+            &self.literals,
+        );
+        align::merge(&mut self.literals, alignments);
+
+        if self.file_kind == FileKind::Gemfile && self.config.formatting.gemfile.check_duplicates {
+            for duplicate in gemfile::duplicates(ast) {
+                self.diagnostics.push(Diagnostic {
+                    message: format!(
+                        "gem '{}' is already declared on line {}",
+                        duplicate.name, duplicate.first.location.start_line
+                    ),
+                    location: duplicate.repeated.location.clone(),
+                });
+            }
+        }
 
+        self.emit_magic_comments(ast)?;
         self.emit_node(ast, 0)?;
 
         let last_code_line = Self::find_last_code_line(ast);
@@ -69,9 +201,86 @@ impl Emitter {
             self.buffer.push('\n');
         }
 
+        if self.config.formatting.comments.align_trailing_comments {
+            return Ok(comment::align_trailing(
+                &self.buffer,
+                &self.trailing_comments,
+                self.config.formatting.line_length,
+            ));
+        }
         Ok(std::mem::take(&mut self.buffer))
     }
 
+    /// Write the shebang and the magic comments at the head of the file in canonical
+    /// order, followed by one blank line
+    ///
+    /// The head ends at the first comment that is not a magic comment. Files without
+    /// magic comments are left alone unless `insert_frozen_string_literal` is on.
+    /// Ruby reads the encoding only from the first line, or the second after a
+    /// shebang, so an encoding comment anywhere else is an ordinary comment.
+    fn emit_magic_comments(&mut self, ast: &Node) -> Result<()> {
+        let first_code_line = ast
+            .children
+            .first()
+            .map_or(usize::MAX, |node| node.location.start_line);
+        let mut head: Vec<usize> = (0..self.all_comments.len())
+            .filter(|&idx| self.all_comments[idx].location.start_line < first_code_line)
+            .collect();
+        head.sort_by_key(|&idx| self.all_comments[idx].location.start_line);
+
+        let mut shebang = None;
+        let mut emitted = Vec::new();
+        let mut magic = Vec::new();
+        for &idx in &head {
+            let comment = &self.all_comments[idx];
+            if comment.location.start_line == 1 && comment.text.starts_with("#!") {
+                shebang = Some(idx);
+                continue;
+            }
+            let Some(parsed) = magic::parse(&comment.text) else {
+                break;
+            };
+            let encoding_line = if shebang.is_some() { 2 } else { 1 };
+            if comment.location.start_line != encoding_line
+                && parsed.iter().any(MagicComment::is_encoding)
+            {
+                break;
+            }
+            emitted.push(idx);
+            magic.extend(parsed);
+        }
+
+        // Ruby honors `frozen_string_literal` anywhere above the first line of code,
+        // such as below a license header that ends the head
+        let declares_frozen = head.iter().any(|&idx| {
+            magic::parse(&self.all_comments[idx].text)
+                .is_some_and(|parsed| parsed.iter().any(MagicComment::is_frozen_string_literal))
+        });
+        let has_code = first_code_line != usize::MAX;
+        let insert = self.config.formatting.style.insert_frozen_string_literal;
+        if insert && has_code && !declares_frozen {
+            magic.push(MagicComment::frozen_string_literal());
+        }
+        if magic.is_empty() {
+            return Ok(());
+        }
+        magic.sort_by_key(MagicComment::rank);
+
+        if let Some(idx) = shebang {
+            writeln!(self.buffer, "{}", self.all_comments[idx].text)?;
+            emitted.push(idx);
+        }
+        for comment in &magic {
+            writeln!(self.buffer, "{}", comment.text())?;
+        }
+        self.emitted_comment_indices.extend(emitted);
+
+        if has_code {
+            self.buffer.push('\n');
+        }
+        Ok(())
+    }
+
     /// Find the last line of code in the AST (excluding comments)
     fn find_last_code_line(ast: &Node) -> usize {
         let mut max_line = ast.location.end_line;
@@ -115,7 +324,7 @@ impl Emitter {
                 }
             }
 
-            writeln!(self.buffer, "{}", comment.text)?;
+            writeln!(self.buffer, "{}", self.comment_text(idx, 0))?;
             self.emitted_comment_indices.insert(idx);
             last_end_line = Some(comment.location.end_line);
             is_first_comment = false;
@@ -216,7 +425,8 @@ impl Emitter {
             writeln!(
                 self.buffer,
                 "{}{}",
-                &self.indent_cache[indent_level], &self.all_comments[idx].text
+                &self.indent_cache[indent_level],
+                self.comment_text(idx, indent_level)
             )?;
             self.emitted_comment_indices.insert(idx);
             last_comment_end_line = Some(comment_end_line);
@@ -305,7 +515,8 @@ impl Emitter {
             writeln!(
                 self.buffer,
                 "{}{}",
-                &self.indent_cache[indent_level], &self.all_comments[idx].text
+                &self.indent_cache[indent_level],
+                self.comment_text(idx, indent_level)
             )?;
             self.emitted_comment_indices.insert(idx);
             last_emitted_line = Some(comment_end_line);
@@ -318,38 +529,42 @@ impl Emitter {
     /// A standalone comment is one that should appear on its own line,
     /// not attached to the end of a code statement
     fn is_standalone_comment(&self, comment: &Comment) -> bool {
-        let comment_line = comment.location.start_line;
-        let _comment_start_offset = comment.location.start_offset;
-
-        // Get the source lines to analyze the comment's position
-        let lines: Vec<&str> = self.source.lines().collect();
-
-        // Check if we have a valid line number (1-indexed to 0-indexed)
-        if comment_line == 0 || comment_line > lines.len() {
+        // Only whitespace may precede the comment on its line
+        let Some(before) = self.source.get(..comment.location.start_offset) else {
             return false;
-        }
-
-        let line = lines[comment_line - 1]; // Convert to 0-indexed
-
-        // Find where the comment starts within the line
-        let comment_text = &comment.text;
-
-        // Look for the comment marker (#) in the line
-        if let Some(hash_pos) = line.find('#') {
-            // Check if there's only whitespace before the comment
-            let before_comment = &line[..hash_pos];
-            let is_only_whitespace = before_comment.trim().is_empty();
-
-            // Also verify this is actually our comment by checking the text matches
-            let line_comment_text = &line[hash_pos..];
-            let is_same_comment = line_comment_text.trim_end() == comment_text.trim_end();
-
-            return is_only_whitespace && is_same_comment;
-        }
+        };
+        before
+            .rsplit('\n')
+            .next()
+            .unwrap_or(before)
+            .trim()
+            .is_empty()
+    }
 
-        // If we can't find the comment marker, assume it's standalone
-        // This is a fallback for edge cases
-        false
+    /// Text of a standalone comment written at `indent_level`
+    ///
+    /// Every line of a multi-line `#` comment gets the indentation, and prose
+    /// paragraphs are refilled to the line length under `comments.reflow`.
+    fn comment_text(&self, idx: usize, indent_level: usize) -> String {
+        let comment = &self.all_comments[idx];
+        if matches!(comment.comment_type, CommentType::Block) {
+            return comment.text.clone();
+        }
+
+        let formatting = &self.config.formatting;
+        let text = if formatting.comments.reflow && comment::is_paragraph(&comment.text) {
+            let width = formatting
+                .line_length
+                .saturating_sub(indent_level * formatting.indent_width);
+            Cow::Owned(comment::reflow(&comment.text, width))
+        } else {
+            Cow::Borrowed(comment.text.as_str())
+        };
+        let indent = self
+            .indent_cache
+            .get(indent_level)
+            .map_or("", String::as_str);
+        text.replace('\n', &format!("\n{}", indent))
     }
 
     /// Check if the node spans only a single line
@@ -359,12 +574,7 @@ impl Emitter {
 
     /// Extract and write source text for a node
     fn write_source_text(&mut self, node: &Node) -> Result<()> {
-        let start = node.location.start_offset;
-        let end = node.location.end_offset;
-        if let Some(text) = self.source.get(start..end) {
-            write!(self.buffer, "{}", text)?;
-        }
-        Ok(())
+        self.write_source(node.location.start_offset, node.location.end_offset)
     }
 
     /// Extract and write trimmed source text for a node
@@ -372,11 +582,115 @@ impl Emitter {
         let start = node.location.start_offset;
         let end = node.location.end_offset;
         if let Some(text) = self.source.get(start..end) {
-            write!(self.buffer, "{}", text.trim())?;
+            let trimmed_start = start + (text.len() - text.trim_start().len());
+            let trimmed_end = start + text.trim_end().len();
+            self.write_source(trimmed_start, trimmed_end.max(trimmed_start))?;
+        }
+        Ok(())
+    }
+
+    /// Copy `source[start..end]` to the buffer verbatim, except that `<<~` heredoc
+    /// bodies are re-indented to follow the line that opens them. `<<-` and `<<`
+    /// bodies are copied byte-exact.
+    fn write_source(&mut self, start: usize, end: usize) -> Result<()> {
+        if self.source.get(start..end).is_none() {
+            return Ok(());
+        }
+
+        let mut pos = start;
+        // Owner line start and its (old, new) indentation, shared by heredocs opened on one line
+        let mut owner: Option<(usize, String, String)> = None;
+
+        for i in 0..self.heredocs.len() {
+            let heredoc = self.heredocs[i];
+            if heredoc.body_start < pos || heredoc.body_start >= end {
+                continue;
+            }
+
+            self.copy_source(pos, heredoc.body_start);
+            pos = heredoc.end.min(end);
+            let body = &self.source[heredoc.body_start..pos];
+
+            // Only move bodies whose opener is part of this copy; otherwise the owner
+            // line was written elsewhere and its new indentation is unknown
+            if heredoc.style != HeredocStyle::Squiggly
+                || heredoc.opener < start
+                || pos != heredoc.end
+            {
+                self.buffer.push_str(body);
+                continue;
+            }
+
+            if owner.as_ref().map(|(line, _, _)| *line) != Some(heredoc.owner_line_start) {
+                owner = Some((
+                    heredoc.owner_line_start,
+                    leading_whitespace(&self.source[heredoc.owner_line_start..]).to_string(),
+                    leading_whitespace(last_line(&self.buffer)).to_string(),
+                ));
+            }
+
+            let (_, old_indent, new_indent) = owner.as_ref().expect("owner was just set");
+            match heredoc::reindent_squiggly(body, old_indent, new_indent) {
+                Some(moved) => self.buffer.push_str(&moved),
+                None => self.buffer.push_str(body),
+            }
         }
+
+        self.copy_source(pos, end);
         Ok(())
     }
 
+    /// The source as seen through the rewritten literals
+    fn rewritten_source(&self) -> Source<'_> {
+        Source::new(&self.source, &self.literals)
+    }
+
+    /// Copy `source[start..end]` to the buffer, rewriting the literals that lie
+    /// entirely inside it
+    fn copy_source(&mut self, start: usize, end: usize) {
+        let mut pos = start;
+        let first = self
+            .literals
+            .partition_point(|literal| literal.start < start);
+        if self
+            .literals
+            .get(first)
+            .is_some_and(|literal| literal.end <= end)
+        {
+            self.ensure_indent_cache(1);
+        }
+
+        for i in first..self.literals.len() {
+            let (literal_start, literal_end) = (self.literals[i].start, self.literals[i].end);
+            if literal_end > end {
+                break;
+            }
+            self.push_source(pos, literal_start);
+            let line = self.buffer.rsplit('\n').next().unwrap_or("");
+            let text = self.literals[i].render(
+                line.chars().count(),
+                leading_whitespace(line),
+                &self.indent_cache[1],
+                self.config.formatting.line_length,
+            );
+            self.buffer.push_str(&text);
+            pos = literal_end;
+        }
+        self.push_source(pos, end);
+    }
+
+    /// Append `source[start..end]` to the buffer, noting where the comments awaited
+    /// by `write_node_source` land
+    fn push_source(&mut self, start: usize, end: usize) {
+        let at = self.buffer.len();
+        for (offset, copied) in &mut self.copied_comments {
+            if (start..end).contains(offset) {
+                *copied = Some(at + *offset - start);
+            }
+        }
+        self.buffer.push_str(&self.source[start..end]);
+    }
+
     /// Emit comments that are within a given line range, preserving blank lines from prev_line
     /// Uses BTreeMap index for O(log n) lookup instead of O(n) iteration
     fn emit_comments_in_range_with_prev_line(
@@ -413,7 +727,8 @@ impl Emitter {
             writeln!(
                 self.buffer,
                 "{}{}",
-                &self.indent_cache[indent_level], &self.all_comments[idx].text
+                &self.indent_cache[indent_level],
+                self.comment_text(idx, indent_level)
             )?;
             self.emitted_comment_indices.insert(idx);
             last_end_line = comment_end_line;
@@ -433,15 +748,25 @@ impl Emitter {
 
         // Now emit the collected comments by accessing text at write time
         for idx in indices_to_emit {
-            write!(self.buffer, " {}", &self.all_comments[idx].text)?;
-            self.emitted_comment_indices.insert(idx);
+            self.write_trailing_comment(idx)?;
         }
 
         Ok(())
     }
 
+    /// Write a comment after the code on the current line, remembering where it
+    /// starts for `align_trailing_comments`
+    fn write_trailing_comment(&mut self, idx: usize) -> Result<()> {
+        self.trailing_comments.push(self.buffer.len());
+        write!(self.buffer, " {}", &self.all_comments[idx].text)?;
+        self.emitted_comment_indices.insert(idx);
+        Ok(())
+    }
+
     /// Emit a node with given indentation level
     fn emit_node(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        self.deadline.check()?;
+
         match &node.node_type {
             NodeType::ProgramNode => self.emit_program(node, indent_level)?,
             NodeType::StatementsNode => self.emit_statements(node, indent_level)?,
@@ -451,6 +776,32 @@ impl Emitter {
             NodeType::IfNode => self.emit_if_unless(node, indent_level, false, "if")?,
             NodeType::UnlessNode => self.emit_if_unless(node, indent_level, false, "unless")?,
             NodeType::CallNode => self.emit_call(node, indent_level)?,
+            NodeType::AndNode | NodeType::OrNode => self.emit_operation(node, indent_level)?,
+            NodeType::LocalVariableWriteNode
+            | NodeType::LocalVariableOperatorWriteNode
+            | NodeType::LocalVariableOrWriteNode
+            | NodeType::LocalVariableAndWriteNode
+            | NodeType::InstanceVariableWriteNode
+            | NodeType::InstanceVariableOperatorWriteNode
+            | NodeType::InstanceVariableOrWriteNode
+            | NodeType::InstanceVariableAndWriteNode
+            | NodeType::ClassVariableWriteNode
+            | NodeType::ClassVariableOperatorWriteNode
+            | NodeType::ClassVariableOrWriteNode
+            | NodeType::ClassVariableAndWriteNode
+            | NodeType::GlobalVariableWriteNode
+            | NodeType::GlobalVariableOperatorWriteNode
+            | NodeType::GlobalVariableOrWriteNode
+            | NodeType::GlobalVariableAndWriteNode
+            | NodeType::ConstantWriteNode
+            | NodeType::ConstantOperatorWriteNode
+            | NodeType::ConstantOrWriteNode
+            | NodeType::ConstantAndWriteNode
+            | NodeType::ConstantPathWriteNode
+            | NodeType::ConstantPathOperatorWriteNode
+            | NodeType::ConstantPathOrWriteNode
+            | NodeType::ConstantPathAndWriteNode => self.emit_assignment(node, indent_level)?,
+            NodeType::ReturnNode => self.emit_return(node, indent_level)?,
             NodeType::BeginNode => self.emit_begin(node, indent_level)?,
             NodeType::RescueNode => self.emit_rescue(node, indent_level)?,
             NodeType::EnsureNode => self.emit_ensure(node, indent_level)?,
@@ -463,6 +814,8 @@ impl Emitter {
             NodeType::SingletonClassNode => self.emit_singleton_class(node, indent_level)?,
             NodeType::CaseMatchNode => self.emit_case_match(node, indent_level)?,
             NodeType::InNode => self.emit_in(node, indent_level)?,
+            NodeType::MatchRequiredNode => self.emit_pattern_match(node, indent_level, "=>")?,
+            NodeType::MatchPredicateNode => self.emit_pattern_match(node, indent_level, "in")?,
             _ => self.emit_generic(node, indent_level)?,
         }
         Ok(())
@@ -470,8 +823,10 @@ impl Emitter {
 
     /// Emit program node (root)
     fn emit_program(&mut self, node: &Node, indent_level: usize) -> Result<()> {
-        for (i, child) in node.children.iter().enumerate() {
-            self.emit_node(child, indent_level)?;
+        let mut i = 0;
+        while i < node.children.len() {
+            i = self.emit_statement_at(&node.children, i, indent_level)?;
+            let child = &node.children[i];
 
             // Add newlines between top-level statements, normalizing to max 1 blank line
             if i < node.children.len() - 1 {
@@ -485,14 +840,190 @@ impl Emitter {
                     self.buffer.push('\n');
                 }
             }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// Emit `statements[i]`, or the run of `require` lines or gem declarations
+    /// starting there in sorted order, and return the index of the last statement
+    /// emitted
+    fn emit_statement_at(
+        &mut self,
+        statements: &[Node],
+        i: usize,
+        indent_level: usize,
+    ) -> Result<usize> {
+        let run = self.sorted_run(&statements[i..]);
+        if run > 1 {
+            self.emit_sorted_run(&statements[i..i + run], indent_level)?;
+            return Ok(i + run - 1);
+        }
+        self.emit_node(&statements[i], indent_level)?;
+        Ok(i)
+    }
+
+    /// Where `node` goes in a sorted run: a `require` line under `sort_requires`,
+    /// or a gem declaration in a Gemfile under `gemfile.sort_gems`
+    fn run_key<'a>(&self, node: &'a Node) -> Option<RunKey<'a>> {
+        if self.config.formatting.style.sort_requires {
+            if let Some((group, path)) = requires::required(node) {
+                return Some(RunKey::Require(group, path));
+            }
+        }
+        if self.file_kind == FileKind::Gemfile && self.config.formatting.gemfile.sort_gems {
+            let name = gemfile::gem_name(node)?;
+            return Some(RunKey::Gem(
+                node.metadata.get("name")?,
+                name.to_ascii_lowercase(),
+            ));
+        }
+        None
+    }
+
+    /// Length of the run of one-line statements with matching sort keys at the
+    /// start of `statements`. A blank line ends a run; comment lines do not.
+    fn sorted_run(&self, statements: &[Node]) -> usize {
+        let Some(first) = statements.first().and_then(|node| self.run_key(node)) else {
+            return 0;
+        };
+
+        let mut len = 0;
+        while let Some(node) = statements.get(len) {
+            if !self.is_single_line(node)
+                || !self.run_key(node).is_some_and(|key| key.same_run(&first))
+            {
+                break;
+            }
+            if let Some(prev) = len.checked_sub(1).map(|prev| &statements[prev]) {
+                let mut gap = (prev.location.end_line + 1)..node.location.start_line;
+                if !gap.all(|line| self.comments_by_line.contains_key(&line)) {
+                    break;
+                }
+            }
+            len += 1;
+        }
+        len
+    }
+
+    /// Emit a run of `require` lines sorted by group (stdlib, gems, relative) and
+    /// path, or of gem declarations sorted by name. Comments directly above a line
+    /// move with it, except those above the first line of the run, which stay on top.
+    fn emit_sorted_run(&mut self, run: &[Node], indent_level: usize) -> Result<()> {
+        self.emit_comments_before(run[0].location.start_line, indent_level)?;
+        self.ensure_indent_cache(indent_level);
+
+        let mut order: Vec<usize> = (0..run.len()).collect();
+        order.sort_by_key(|&i| self.run_key(&run[i]));
+
+        for (n, &i) in order.iter().enumerate() {
+            if n > 0 {
+                self.buffer.push('\n');
+            }
+            let node = &run[i];
+            if i > 0 {
+                let above = run[i - 1].location.end_line + 1;
+                for idx in self.get_comment_indices_in_range(above, node.location.start_line) {
+                    writeln!(
+                        self.buffer,
+                        "{}{}",
+                        &self.indent_cache[indent_level],
+                        self.comment_text(idx, indent_level)
+                    )?;
+                    self.emitted_comment_indices.insert(idx);
+                }
+            }
+            self.emit_indent(indent_level)?;
+            match self.gem_declaration(node) {
+                Some(declaration) => self.buffer.push_str(&declaration),
+                None => self.write_source_text(node)?,
+            }
+            self.emit_trailing_comments(node.location.end_line)?;
         }
         Ok(())
     }
 
+    /// A one-line gem declaration in a Gemfile or gemspec with normalized
+    /// argument spacing
+    fn gem_declaration(&self, node: &Node) -> Option<String> {
+        if self.file_kind != FileKind::Gemfile || !self.is_single_line(node) {
+            return None;
+        }
+        gemfile::declaration(node, &self.rewritten_source())
+    }
+
     /// Emit statements node (body of class/module/def)
     fn emit_statements(&mut self, node: &Node, indent_level: usize) -> Result<()> {
-        for (i, child) in node.children.iter().enumerate() {
-            self.emit_node(child, indent_level)?;
+        self.emit_statement_list(node, indent_level, None)
+    }
+
+    /// Emit the statements of a class, module, singleton class or method body,
+    /// applying the `blank_lines` rules and access modifier indentation
+    fn emit_body_statements(
+        &mut self,
+        node: &Node,
+        indent_level: usize,
+        definition: &Node,
+    ) -> Result<()> {
+        self.emit_statement_list(node, indent_level, Some(definition))
+    }
+
+    /// How the access modifiers of `node`, the body of `definition`, are laid out
+    fn modifier_layout(&self, node: &Node, definition: Option<&Node>) -> ModifierLayout {
+        match definition {
+            Some(definition)
+                if matches!(
+                    definition.node_type,
+                    NodeType::ClassNode | NodeType::ModuleNode | NodeType::SingletonClassNode
+                ) =>
+            {
+                body::modifier_layout(
+                    &node.children,
+                    definition.location.start_column,
+                    &self.config.formatting.style.access_modifier_indentation,
+                )
+            }
+            _ => ModifierLayout::Flat,
+        }
+    }
+
+    /// Level of the comments before the `end` of `definition`, whose body is at
+    /// `indent_level`: one deeper when the body ends in an indented modifier section
+    fn end_comments_level(&self, definition: &Node, indent_level: usize) -> usize {
+        let body = definition
+            .children
+            .iter()
+            .find(|child| matches!(child.node_type, NodeType::StatementsNode));
+        match body {
+            Some(body)
+                if self.modifier_layout(body, Some(definition)) == ModifierLayout::Indent
+                    && body::open_sections(&body.children).last() == Some(&true) =>
+            {
+                indent_level + 1
+            }
+            _ => indent_level,
+        }
+    }
+
+    fn emit_statement_list(
+        &mut self,
+        node: &Node,
+        indent_level: usize,
+        definition: Option<&Node>,
+    ) -> Result<()> {
+        let layout = self.modifier_layout(node, definition);
+        let sections = body::open_sections(&node.children);
+
+        let mut i = 0;
+        while i < node.children.len() {
+            let is_modifier = body::is_access_modifier(&node.children[i]);
+            let level = match layout {
+                ModifierLayout::Outdent if is_modifier => indent_level.saturating_sub(1),
+                ModifierLayout::Indent if sections[i] && !is_modifier => indent_level + 1,
+                _ => indent_level,
+            };
+            i = self.emit_statement_at(&node.children, i, level)?;
+            let child = &node.children[i];
 
             if i < node.children.len() - 1 {
                 let current_end_line = child.location.end_line;
@@ -511,16 +1042,34 @@ impl Emitter {
                 let effective_next_line = first_comment_line.unwrap_or(next_start_line);
                 let line_diff = effective_next_line.saturating_sub(current_end_line);
 
-                let newlines = if line_diff > 1 { 2 } else { 1 };
+                let mut blank_line = line_diff > 1;
+                if definition.is_some() {
+                    blank_line = body::blank_line_between(
+                        child,
+                        next_child,
+                        blank_line,
+                        &self.config.formatting.blank_lines,
+                    );
+                }
+                let newlines = if blank_line { 2 } else { 1 };
 
                 for _ in 0..newlines {
                     self.buffer.push('\n');
                 }
             }
+            i += 1;
         }
         Ok(())
     }
 
+    /// Emit a child of a class, module or method definition
+    fn emit_body(&mut self, node: &Node, indent_level: usize, definition: &Node) -> Result<()> {
+        match node.node_type {
+            NodeType::StatementsNode => self.emit_body_statements(node, indent_level, definition),
+            _ => self.emit_node(node, indent_level),
+        }
+    }
+
     /// Emit class definition
     fn emit_class(&mut self, node: &Node, indent_level: usize) -> Result<()> {
         // Emit any comments before this class
@@ -556,11 +1105,12 @@ impl Emitter {
                 continue;
             }
             has_body_content = true;
-            self.emit_node(child, indent_level + 1)?;
+            self.emit_body(child, indent_level + 1, node)?;
         }
 
         // Emit comments that appear before the end statement while preserving their position
-        self.emit_comments_before_end(class_start_line, class_end_line, indent_level + 1)?;
+        let level = self.end_comments_level(node, indent_level + 1);
+        self.emit_comments_before_end(class_start_line, class_end_line, level)?;
 
         // Add newline before end if there was body content or internal comments
         if (has_body_content || self.has_comments_in_range(class_start_line + 1, class_end_line))
@@ -603,11 +1153,12 @@ impl Emitter {
                 continue;
             }
             has_body_content = true;
-            self.emit_node(child, indent_level + 1)?;
+            self.emit_body(child, indent_level + 1, node)?;
         }
 
         // Emit comments that appear before the end statement while preserving their position
-        self.emit_comments_before_end(module_start_line, module_end_line, indent_level + 1)?;
+        let level = self.end_comments_level(node, indent_level + 1);
+        self.emit_comments_before_end(module_start_line, module_end_line, level)?;
 
         // Add newline before end if there was body content or internal comments
         if (has_body_content || self.has_comments_in_range(module_start_line + 1, module_end_line))
@@ -629,32 +1180,41 @@ impl Emitter {
         // Emit any comments before this method
         self.emit_comments_before(node.location.start_line, indent_level)?;
 
-        self.emit_indent(indent_level)?;
-        write!(self.buffer, "def ")?;
-
-        // Handle class methods (def self.method_name)
-        if let Some(receiver) = node.metadata.get("receiver") {
-            write!(self.buffer, "{}.", receiver)?;
-        }
-
-        if let Some(name) = node.metadata.get("name") {
-            write!(self.buffer, "{}", name)?;
-        }
-
-        // Emit parameters using metadata from prism_bridge
-        if let Some(params_text) = node.metadata.get("parameters_text") {
-            let has_parens = node
-                .metadata
-                .get("has_parens")
-                .map(|v| v == "true")
-                .unwrap_or(false);
-            if has_parens {
-                write!(self.buffer, "({})", params_text)?;
-            } else {
-                write!(self.buffer, " {}", params_text)?;
+        let is_endless = node
+            .metadata
+            .get("endless")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        // `def foo; end` or `def foo; body; end`; a rescue clause keeps the block form
+        let is_one_liner = !is_endless
+            && self.is_single_line(node)
+            && !node
+                .children
+                .iter()
+                .any(|c| matches!(c.node_type, NodeType::BeginNode));
+
+        match self.config.formatting.style.method_style {
+            MethodStyle::Expanded => {}
+            MethodStyle::Preserve => {
+                if is_endless {
+                    return self.emit_endless_method(node, indent_level);
+                }
+                if is_one_liner {
+                    return self.emit_one_liner_method(node, indent_level);
+                }
+            }
+            MethodStyle::Endless => {
+                if is_endless || self.can_be_endless(node, indent_level) {
+                    return self.emit_endless_method(node, indent_level);
+                }
+                if is_one_liner {
+                    return self.emit_one_liner_method(node, indent_level);
+                }
             }
         }
 
+        self.emit_method_signature(node, indent_level)?;
+
         // Emit trailing comment on same line as def
         self.emit_trailing_comments(node.location.start_line)?;
         self.buffer.push('\n');
@@ -664,7 +1224,7 @@ impl Emitter {
             if self.is_structural_node(&child.node_type) {
                 continue;
             }
-            self.emit_node(child, indent_level + 1)?;
+            self.emit_body(child, indent_level + 1, node)?;
         }
 
         // Emit comments that appear before the end statement while preserving their position
@@ -691,72 +1251,275 @@ impl Emitter {
         Ok(())
     }
 
-    /// Emit begin node
-    /// BeginNode can be either:
-    /// 1. Explicit begin...end block (source starts with "begin")
-    /// 2. Implicit begin wrapping method body with rescue/ensure
-    fn emit_begin(&mut self, node: &Node, indent_level: usize) -> Result<()> {
-        // Check if this is an explicit begin block by looking at source
-        let is_explicit_begin = if !self.source.is_empty() {
-            self.source
-                .get(node.location.start_offset..)
-                .map(|s| s.trim_start().starts_with("begin"))
-                .unwrap_or(false)
-        } else {
-            false
-        };
+    /// Emit `def receiver.name(params)` at the given indentation
+    fn emit_method_signature(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        self.emit_indent(indent_level)?;
+        write!(self.buffer, "def ")?;
 
-        if is_explicit_begin {
-            self.emit_comments_before(node.location.start_line, indent_level)?;
-            self.emit_indent(indent_level)?;
-            writeln!(self.buffer, "begin")?;
+        // Handle class methods (def self.method_name)
+        if let Some(receiver) = node.metadata.get("receiver") {
+            write!(self.buffer, "{}.", receiver)?;
+        }
 
-            for child in &node.children {
-                self.emit_node(child, indent_level + 1)?;
-                self.buffer.push('\n');
+        if let Some(name) = node.metadata.get("name") {
+            write!(self.buffer, "{}", name)?;
+        }
+
+        // Emit parameters rebuilt from the parameter nodes, falling back to the
+        // parameters text captured by prism_bridge
+        if !self.emit_method_parameters(node, indent_level)? {
+            // Comments inside the list are part of the copied text
+            if let Some((start, end)) = Self::parameters_range(node) {
+                for (idx, comment) in self.all_comments.iter().enumerate() {
+                    if comment.location.start_offset >= start && comment.location.start_offset < end
+                    {
+                        self.emitted_comment_indices.insert(idx);
+                    }
+                }
             }
 
-            self.emit_indent(indent_level)?;
-            write!(self.buffer, "end")?;
-            // Emit trailing comments on end line
-            self.emit_trailing_comments(node.location.end_line)?;
-        } else {
-            // Implicit begin - emit children directly
-            for (i, child) in node.children.iter().enumerate() {
-                if i > 0 {
-                    self.buffer.push('\n');
+            if let Some(params_text) = node.metadata.get("parameters_text") {
+                let has_parens = node
+                    .metadata
+                    .get("has_parens")
+                    .map(|v| v == "true")
+                    .unwrap_or(false);
+                if has_parens {
+                    write!(self.buffer, "({})", params_text)?;
+                } else {
+                    write!(self.buffer, " {}", params_text)?;
                 }
-                self.emit_node(child, indent_level)?;
             }
         }
+
         Ok(())
     }
 
-    /// Emit rescue node
-    fn emit_rescue(&mut self, node: &Node, indent_level: usize) -> Result<()> {
-        // Rescue node structure:
-        // - First children are exception class references (ConstantReadNode)
-        // - Then exception variable (LocalVariableTargetNode)
-        // - Last child is StatementsNode with the rescue body
+    /// The single statement of a method body, if that is all it has
+    fn single_body_statement(node: &Node) -> Option<&Node> {
+        let body = node
+            .children
+            .iter()
+            .find(|c| matches!(c.node_type, NodeType::StatementsNode))?;
+        match body.children.as_slice() {
+            [statement] => Some(statement),
+            _ => None,
+        }
+    }
 
-        // Dedent by 1 level since rescue is at the same level as method body
-        let rescue_indent = indent_level.saturating_sub(1);
-        self.emit_indent(rescue_indent)?;
-        write!(self.buffer, "rescue")?;
+    /// Emit an endless method: `def name(params) = expression`
+    fn emit_endless_method(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        self.emit_method_signature(node, indent_level)?;
 
-        // Extract exception classes and variable from source
-        // Handle multi-line rescue clauses (e.g., multiple exception classes spanning lines)
-        if !self.source.is_empty() && node.location.end_offset <= self.source.len() {
-            if let Some(source_text) = self
-                .source
-                .get(node.location.start_offset..node.location.end_offset)
-            {
-                // Find the rescue declaration part (first line only, unless trailing comma/backslash)
-                let mut rescue_decl = String::new();
-                let mut expect_continuation = false;
+        if let Some(body) = node
+            .children
+            .iter()
+            .find(|c| matches!(c.node_type, NodeType::StatementsNode))
+        {
+            write!(self.buffer, " = ")?;
+            self.write_source_text_trimmed(body)?;
+        }
 
-                for line in source_text.lines() {
-                    let trimmed = line.trim();
+        self.emit_trailing_comments(node.location.end_line)?;
+        Ok(())
+    }
+
+    /// Emit a method written on one line: `def name; end` or `def name; body; end`
+    fn emit_one_liner_method(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        self.emit_method_signature(node, indent_level)?;
+
+        if let Some(body) = node
+            .children
+            .iter()
+            .find(|c| matches!(c.node_type, NodeType::StatementsNode))
+        {
+            write!(self.buffer, "; ")?;
+            self.write_source_text_trimmed(body)?;
+        }
+
+        write!(self.buffer, "; end")?;
+        self.emit_trailing_comments(node.location.end_line)?;
+        Ok(())
+    }
+
+    /// Whether a method can be rewritten as `def name(params) = expression`: a single
+    /// one-line expression that binds tighter than `def ... =`, no setter name, no
+    /// comments inside, and the result fits in `line_length`
+    fn can_be_endless(&self, node: &Node, indent_level: usize) -> bool {
+        let Some(statement) = Self::single_body_statement(node) else {
+            return false;
+        };
+
+        if !endless::is_endless_body(statement, &self.source) {
+            return false;
+        }
+
+        let name = node.metadata.get("name").map(String::as_str).unwrap_or("");
+        let is_setter = name.ends_with('=') && !matches!(name, "==" | "!=" | "<=" | ">=" | "===");
+        if name.is_empty() || is_setter {
+            return false;
+        }
+
+        if self.has_comments_in_range(node.location.start_line + 1, node.location.end_line + 1) {
+            return false;
+        }
+
+        let Some(body_text) = self
+            .source
+            .get(statement.location.start_offset..statement.location.end_offset)
+        else {
+            return false;
+        };
+
+        let params = Self::parameter_nodes(node)
+            .map(|param| parameters::format_parameter(param, &self.rewritten_source()))
+            .collect::<Option<Vec<_>>>();
+        let params_text = match params {
+            Some(params) if params.is_empty() => String::new(),
+            Some(params) => format!("({})", params.join(", ")),
+            None => return false,
+        };
+
+        let receiver = node
+            .metadata
+            .get("receiver")
+            .map(|r| format!("{}.", r))
+            .unwrap_or_default();
+        let line = format!("def {}{}{} = {}", receiver, name, params_text, body_text);
+
+        !line.contains('\n')
+            && indent_level * self.config.formatting.indent_width + line.chars().count()
+                <= self.config.formatting.line_length
+    }
+
+    /// Emit a parenthesized, normalized parameter list: `(a, b = 1, *rest, key:, &blk)`
+    ///
+    /// Parameters go one per line when the signature would exceed `line_length`.
+    /// Returns `false` without writing anything when the list has to be copied from
+    /// the source instead (comments inside it, multi-line defaults).
+    fn emit_method_parameters(&mut self, node: &Node, indent_level: usize) -> Result<bool> {
+        let Some((params_start, params_end)) = Self::parameters_range(node) else {
+            return Ok(false);
+        };
+
+        if self.all_comments.iter().any(|c| {
+            c.location.start_offset >= params_start && c.location.start_offset < params_end
+        }) {
+            return Ok(false);
+        }
+
+        let Some(formatted) = Self::parameter_nodes(node)
+            .map(|param| parameters::format_parameter(param, &self.rewritten_source()))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(false);
+        };
+
+        let single_line = formatted.join(", ");
+        let width = self.current_column() + single_line.chars().count() + 2;
+
+        if formatted.len() < 2 || width <= self.config.formatting.line_length {
+            write!(self.buffer, "({})", single_line)?;
+            return Ok(true);
+        }
+
+        self.buffer.push('(');
+        for (i, param) in formatted.iter().enumerate() {
+            self.buffer.push('\n');
+            self.emit_indent(indent_level + 1)?;
+            self.buffer.push_str(param);
+            if i < formatted.len() - 1 {
+                self.buffer.push(',');
+            }
+        }
+        self.buffer.push('\n');
+        self.emit_indent(indent_level)?;
+        self.buffer.push(')');
+
+        Ok(true)
+    }
+
+    /// Parameter nodes of a method definition (every child except the body)
+    fn parameter_nodes(node: &Node) -> impl Iterator<Item = &Node> {
+        node.children
+            .iter()
+            .filter(|c| !matches!(c.node_type, NodeType::StatementsNode | NodeType::BeginNode))
+    }
+
+    /// Source range from the first parameter to the end of the last one
+    fn parameters_range(node: &Node) -> Option<(usize, usize)> {
+        let mut params = Self::parameter_nodes(node);
+        let first = params.next()?;
+        let end = params.last().unwrap_or(first).location.end_offset;
+        Some((first.location.start_offset, end))
+    }
+
+    /// Emit begin node
+    /// BeginNode can be either:
+    /// 1. Explicit begin...end block (source starts with "begin")
+    /// 2. Implicit begin wrapping method body with rescue/ensure
+    fn emit_begin(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        // Check if this is an explicit begin block by looking at source
+        let is_explicit_begin = if !self.source.is_empty() {
+            self.source
+                .get(node.location.start_offset..)
+                .map(|s| s.trim_start().starts_with("begin"))
+                .unwrap_or(false)
+        } else {
+            false
+        };
+
+        if is_explicit_begin {
+            self.emit_comments_before(node.location.start_line, indent_level)?;
+            self.emit_indent(indent_level)?;
+            writeln!(self.buffer, "begin")?;
+
+            for child in &node.children {
+                self.emit_node(child, indent_level + 1)?;
+                self.buffer.push('\n');
+            }
+
+            self.emit_indent(indent_level)?;
+            write!(self.buffer, "end")?;
+            // Emit trailing comments on end line
+            self.emit_trailing_comments(node.location.end_line)?;
+        } else {
+            // Implicit begin - emit children directly
+            for (i, child) in node.children.iter().enumerate() {
+                if i > 0 {
+                    self.buffer.push('\n');
+                }
+                self.emit_node(child, indent_level)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit rescue node
+    fn emit_rescue(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        // Rescue node structure:
+        // - First children are exception class references (ConstantReadNode)
+        // - Then exception variable (LocalVariableTargetNode)
+        // - Last child is StatementsNode with the rescue body
+
+        // Dedent by 1 level since rescue is at the same level as method body
+        let rescue_indent = indent_level.saturating_sub(1);
+        self.emit_indent(rescue_indent)?;
+        write!(self.buffer, "rescue")?;
+
+        // Extract exception classes and variable from source
+        // Handle multi-line rescue clauses (e.g., multiple exception classes spanning lines)
+        if !self.source.is_empty() && node.location.end_offset <= self.source.len() {
+            if let Some(source_text) = self
+                .source
+                .get(node.location.start_offset..node.location.end_offset)
+            {
+                // Find the rescue declaration part (first line only, unless trailing comma/backslash)
+                let mut rescue_decl = String::new();
+                let mut expect_continuation = false;
+
+                for line in source_text.lines() {
+                    let trimmed = line.trim();
 
                     if rescue_decl.is_empty() {
                         // First line - remove "rescue" prefix
@@ -872,11 +1635,8 @@ impl Emitter {
                 NodeType::WhenNode | NodeType::ElseNode
             ) {
                 // This is the predicate - extract from source
-                let start = first_child.location.start_offset;
-                let end = first_child.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, " {}", text)?;
-                }
+                write!(self.buffer, " ")?;
+                self.write_source_text(first_child)?;
                 when_start_idx = 1;
             }
         }
@@ -884,17 +1644,73 @@ impl Emitter {
         self.buffer.push('\n');
 
         // Emit when clauses and else
-        for child in node.children.iter().skip(when_start_idx) {
-            match &child.node_type {
-                NodeType::WhenNode => {
-                    self.emit_when(child, indent_level)?;
+        self.emit_case_branches(&node.children[when_start_idx..], indent_level)?;
+
+        // Emit "end" keyword
+        self.emit_indent(indent_level)?;
+        write!(self.buffer, "end")?;
+        // Emit trailing comments on end line
+        self.emit_trailing_comments(node.location.end_line)?;
+
+        Ok(())
+    }
+
+    /// Emit the `when`/`in` branches and `else` of a case, each followed by a newline
+    ///
+    /// One-line branches are chosen by `style.case_when`; with `compact_aligned` the
+    /// `then` of consecutive one-line branches is lined up.
+    fn emit_case_branches(&mut self, branches: &[Node], indent_level: usize) -> Result<()> {
+        let inline: Vec<Option<(String, String)>> = branches
+            .iter()
+            .map(|branch| self.inline_branch(branch, indent_level))
+            .collect();
+
+        let align = matches!(
+            self.config.formatting.style.case_when,
+            CaseWhen::CompactAligned
+        );
+        let mut then_columns = vec![0; branches.len()];
+        let mut run_start = 0;
+        for i in 0..=branches.len() {
+            if inline.get(i).is_some_and(Option::is_some) {
+                continue;
+            }
+            // inline[run_start..i] is a run of one-line branches
+            let widest = inline[run_start..i]
+                .iter()
+                .flatten()
+                .map(|(head, _)| head.chars().count())
+                .max()
+                .unwrap_or(0);
+            for column in &mut then_columns[run_start..i] {
+                *column = if align { widest } else { 0 };
+            }
+            run_start = i + 1;
+        }
+
+        for (i, branch) in branches.iter().enumerate() {
+            match &branch.node_type {
+                NodeType::WhenNode | NodeType::InNode => {
+                    match &inline[i] {
+                        Some((head, body)) => self.emit_inline_branch(
+                            branch,
+                            head,
+                            body,
+                            then_columns[i],
+                            indent_level,
+                        )?,
+                        None if matches!(branch.node_type, NodeType::WhenNode) => {
+                            self.emit_when(branch, indent_level)?
+                        }
+                        None => self.emit_in(branch, indent_level)?,
+                    }
                     self.buffer.push('\n');
                 }
                 NodeType::ElseNode => {
                     self.emit_indent(indent_level)?;
                     writeln!(self.buffer, "else")?;
                     // Emit else body
-                    for else_child in &child.children {
+                    for else_child in &branch.children {
                         if matches!(else_child.node_type, NodeType::StatementsNode) {
                             self.emit_statements(else_child, indent_level + 1)?;
                         } else {
@@ -907,55 +1723,157 @@ impl Emitter {
             }
         }
 
-        // Emit "end" keyword
+        Ok(())
+    }
+
+    /// The head (`when a, b` or `in pattern`) and body of a branch that stays on one
+    /// line as `head then body`, according to `style.case_when`
+    fn inline_branch(&self, node: &Node, indent_level: usize) -> Option<(String, String)> {
+        let style = &self.config.formatting.style.case_when;
+        if matches!(style, CaseWhen::Expand) || !self.is_single_line(node) {
+            return None;
+        }
+        if self.has_comments_between(node.location.start_offset, node.location.end_offset) {
+            return None;
+        }
+
+        let text = |n: &Node| {
+            self.source
+                .get(n.location.start_offset..n.location.end_offset)
+        };
+        let head = match node.node_type {
+            NodeType::WhenNode => {
+                let conditions = Self::when_conditions(node)
+                    .map(text)
+                    .collect::<Option<Vec<_>>>()?;
+                format!("when {}", conditions.join(", "))
+            }
+            NodeType::InNode => format!("in {}", self.pattern_text(node.children.first()?)?),
+            _ => return None,
+        };
+        let statements = node
+            .children
+            .iter()
+            .find(|c| matches!(c.node_type, NodeType::StatementsNode))?;
+
+        let body = text(statements)?.trim().to_string();
+
+        let width = indent_level * self.config.formatting.indent_width
+            + head.chars().count()
+            + " then ".len()
+            + body.chars().count();
+        if matches!(style, CaseWhen::CompactAligned) && width > self.config.formatting.line_length {
+            return None;
+        }
+
+        Some((head, body))
+    }
+
+    /// Emit `head then body`, padding the head to `then_column`
+    fn emit_inline_branch(
+        &mut self,
+        node: &Node,
+        head: &str,
+        body: &str,
+        then_column: usize,
+        indent_level: usize,
+    ) -> Result<()> {
+        self.emit_comments_before(node.location.start_line, indent_level)?;
         self.emit_indent(indent_level)?;
-        write!(self.buffer, "end")?;
-        // Emit trailing comments on end line
-        self.emit_trailing_comments(node.location.end_line)?;
 
+        let padding = then_column.saturating_sub(head.chars().count());
+        let fits = self.current_column()
+            + head.chars().count()
+            + padding
+            + " then ".len()
+            + body.chars().count()
+            <= self.config.formatting.line_length;
+        let padding = if fits { padding } else { 0 };
+
+        write!(self.buffer, "{}{} then {}", head, " ".repeat(padding), body)?;
+        self.emit_trailing_comments(node.location.end_line)?;
         Ok(())
     }
 
-    /// Emit when node
+    /// The conditions of a `when` branch
+    fn when_conditions(node: &Node) -> impl Iterator<Item = &Node> {
+        node.children
+            .iter()
+            .filter(|c| !matches!(c.node_type, NodeType::StatementsNode))
+    }
+
+    /// Emit a `when` branch with its body on the following lines
     fn emit_when(&mut self, node: &Node, indent_level: usize) -> Result<()> {
         self.emit_comments_before(node.location.start_line, indent_level)?;
         self.emit_indent(indent_level)?;
 
         write!(self.buffer, "when ")?;
+        let conditions: Vec<&Node> = Self::when_conditions(node).collect();
+        self.write_when_conditions(&conditions, indent_level)?;
+
+        // Emit trailing comment on same line as the conditions
+        let last_line = conditions
+            .last()
+            .map_or(node.location.start_line, |c| c.location.end_line);
+        self.emit_trailing_comments(last_line)?;
 
-        // Collect conditions (all children except StatementsNode)
-        let conditions: Vec<_> = node
+        if let Some(statements) = node
             .children
             .iter()
-            .filter(|c| !matches!(c.node_type, NodeType::StatementsNode))
-            .collect();
-
-        // Emit conditions with comma separator
-        for (i, cond) in conditions.iter().enumerate() {
-            self.write_source_text(cond)?;
-            if i < conditions.len() - 1 {
-                write!(self.buffer, ", ")?;
-            }
+            .find(|c| matches!(c.node_type, NodeType::StatementsNode))
+        {
+            self.buffer.push('\n');
+            self.emit_statements(statements, indent_level + 1)?;
         }
 
-        let statements = node
-            .children
+        Ok(())
+    }
+
+    /// Write `when` conditions separated by `, `, wrapping after a comma when the line
+    /// would exceed `line_length`; continuation lines line up with the first condition
+    fn write_when_conditions(&mut self, conditions: &[&Node], indent_level: usize) -> Result<()> {
+        let (Some(first), Some(last)) = (conditions.first(), conditions.last()) else {
+            return Ok(());
+        };
+        let (start, end) = (first.location.start_offset, last.location.end_offset);
+
+        let texts: Option<Vec<String>> = conditions
             .iter()
-            .find(|c| matches!(c.node_type, NodeType::StatementsNode));
+            .map(|c| {
+                self.source
+                    .get(c.location.start_offset..c.location.end_offset)
+                    .filter(|text| !text.contains('\n'))
+                    .map(str::to_string)
+            })
+            .collect();
 
-        if self.is_single_line(node) {
-            // Inline style: when X then Y
-            if let Some(statements) = statements {
-                write!(self.buffer, " then ")?;
-                self.write_source_text(statements)?;
+        // Comments and multi-line conditions are copied as written
+        let Some(texts) = texts.filter(|_| !self.has_comments_between(start, end)) else {
+            self.write_source(start, end)?;
+            for (idx, comment) in self.all_comments.iter().enumerate() {
+                if comment.location.start_offset >= start && comment.location.start_offset < end {
+                    self.emitted_comment_indices.insert(idx);
+                }
             }
-        } else {
-            // Multi-line style: when X\n  Y
-            self.buffer.push('\n');
+            return Ok(());
+        };
 
-            if let Some(statements) = statements {
-                self.emit_statements(statements, indent_level + 1)?;
+        let line_length = self.config.formatting.line_length;
+        let continuation = self.current_column();
+        for (i, text) in texts.iter().enumerate() {
+            if i > 0 {
+                // Room for `, text` plus the comma that follows it
+                let comma = usize::from(i + 1 < texts.len());
+                if self.current_column() + 2 + text.chars().count() + comma > line_length {
+                    self.buffer.push_str(",\n");
+                    self.emit_indent(indent_level)?;
+                    let extra = continuation.saturating_sub(self.current_column());
+                    self.buffer.push_str(&" ".repeat(extra));
+                } else {
+                    self.buffer.push_str(", ");
+                }
             }
+            self.buffer.push_str(text);
         }
 
         Ok(())
@@ -997,7 +1915,7 @@ impl Emitter {
 
             // Emit condition
             if let Some(predicate) = node.children.first() {
-                self.write_source_text(predicate)?;
+                self.write_expression(predicate, indent_level, false)?;
             }
 
             return Ok(());
@@ -1016,7 +1934,7 @@ impl Emitter {
 
             // Emit condition
             if let Some(predicate) = node.children.first() {
-                self.write_source_text(predicate)?;
+                self.write_expression(predicate, indent_level, false)?;
             }
 
             write!(self.buffer, " ? ")?;
@@ -1050,7 +1968,7 @@ impl Emitter {
 
             // Emit condition
             if let Some(predicate) = node.children.first() {
-                self.write_source_text(predicate)?;
+                self.write_expression(predicate, indent_level, false)?;
             }
 
             write!(self.buffer, " then ")?;
@@ -1078,9 +1996,10 @@ impl Emitter {
             write!(self.buffer, "{} ", keyword)?;
         }
 
-        // Emit predicate (condition) - first child
+        // Emit predicate (condition) - first child. Its continuation lines go one
+        // level deeper than the body so that the two stay apart.
         if let Some(predicate) = node.children.first() {
-            self.write_source_text(predicate)?;
+            self.write_expression(predicate, indent_level + 1, true)?;
         }
 
         // Emit trailing comment on same line as if/unless/elsif
@@ -1132,38 +2051,304 @@ impl Emitter {
 
     /// Emit method call, handling blocks specially for proper indentation
     fn emit_call(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        if operator::is_operation(node) {
+            return self.emit_operation(node, indent_level);
+        }
+
         // Emit any comments before this call
         self.emit_comments_before(node.location.start_line, indent_level)?;
 
-        // Check if this call has a block (last child is BlockNode)
-        let has_block = node
-            .children
+        if let Some(declaration) = self.gem_declaration(node) {
+            self.emit_indent(indent_level)?;
+            self.buffer.push_str(&declaration);
+            self.emit_trailing_comments(node.location.end_line)?;
+            return Ok(());
+        }
+
+        self.emit_indent(indent_level)?;
+        self.write_call(node, indent_level)?;
+        self.emit_trailing_comments(node.location.end_line)
+    }
+
+    /// The block passed to a call, which is its last child
+    fn call_block(node: &Node) -> Option<&Node> {
+        node.children
             .last()
-            .map(|c| matches!(c.node_type, NodeType::BlockNode))
-            .unwrap_or(false);
+            .filter(|c| matches!(c.node_type, NodeType::BlockNode))
+    }
 
-        if !has_block {
-            // No block - use generic emission (extracts from source)
-            return self.emit_generic_without_comments(node, indent_level);
-        }
+    /// Write a call from the current column, on a line that starts at `indent_level`:
+    /// a long or broken method chain one call per line, a block with the configured
+    /// delimiters, and anything else as written
+    fn write_call(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        let Some(block_node) = Self::call_block(node) else {
+            // Long or already broken method chains get one call per line
+            if let Some(chain) = self.broken_chain(node, node.location.end_offset) {
+                return self.emit_method_chain(&chain, indent_level);
+            }
+            return self.write_node_source(node);
+        };
 
-        // Has block - need to handle specially
-        let block_node = node.children.last().unwrap();
+        // Determine block style (do...end vs { }) from the source and block_delimiters
+        let (block_style, inline) = self.choose_block_style(node, block_node);
 
-        // Determine block style from source (do...end vs { })
-        let block_style = self.detect_block_style(block_node);
+        // `foo bar do ... end` becomes `foo(bar) { ... }` so the block stays with `foo`
+        let add_parens = block_style == BlockStyle::Braces
+            && self.detect_block_style(block_node) == BlockStyle::DoEnd
+            && Self::has_parenless_arguments(node);
 
         // Emit the call part (receiver.method(args)) from source
-        self.emit_call_without_block(node, block_node, indent_level)?;
+        self.write_call_without_block(node, block_node, indent_level, add_parens)?;
 
-        match block_style {
-            BlockStyle::DoEnd => self.emit_do_end_block(block_node, indent_level)?,
-            BlockStyle::Braces => self.emit_brace_block(block_node, indent_level)?,
+        match (block_style, inline) {
+            (BlockStyle::DoEnd, _) => self.emit_do_end_block(block_node, indent_level)?,
+            (BlockStyle::Braces, Some(inline)) => {
+                write!(self.buffer, " {}", inline)?;
+                self.emit_trailing_comments(block_node.location.end_line)?;
+            }
+            (BlockStyle::Braces, None) => self.emit_brace_block(block_node, indent_level)?,
         }
 
         Ok(())
     }
 
+    /// Pick the block delimiters according to `style.block_delimiters`, along with the
+    /// one-line text when the block is collapsed onto the call's line
+    fn choose_block_style(
+        &self,
+        call_node: &Node,
+        block_node: &Node,
+    ) -> (BlockStyle, Option<String>) {
+        let source_style = self.detect_block_style(block_node);
+        let single_line = self.is_single_line(block_node);
+
+        match self.config.formatting.style.block_delimiters {
+            BlockDelimiters::Preserve => (source_style, None),
+            BlockDelimiters::LineCountBased => {
+                match self.inline_block(call_node, block_node) {
+                    // Already a one-line brace block: keep it as written
+                    Some(_) if source_style == BlockStyle::Braces && single_line => {
+                        (BlockStyle::Braces, None)
+                    }
+                    Some(inline) => (BlockStyle::Braces, Some(inline)),
+                    None => (BlockStyle::DoEnd, None),
+                }
+            }
+            BlockDelimiters::Semantic => {
+                let functional = call_node
+                    .metadata
+                    .get("name")
+                    .is_some_and(|name| block::is_functional(name));
+                // `rescue` is only allowed in do...end blocks
+                let has_rescue = block_node
+                    .children
+                    .iter()
+                    .any(|c| matches!(c.node_type, NodeType::BeginNode));
+
+                if !functional || has_rescue {
+                    (BlockStyle::DoEnd, None)
+                } else if source_style == BlockStyle::DoEnd && single_line {
+                    match self.inline_block(call_node, block_node) {
+                        Some(inline) => (BlockStyle::Braces, Some(inline)),
+                        None => (BlockStyle::DoEnd, None),
+                    }
+                } else {
+                    (BlockStyle::Braces, None)
+                }
+            }
+        }
+    }
+
+    /// The block as a one-line `{ |x| body }`, if it has no rescue clause or comments,
+    /// its body is on one line, and the whole call, starting at the current column,
+    /// fits in `line_length`
+    fn inline_block(&self, call_node: &Node, block_node: &Node) -> Option<String> {
+        let (start, end) = (
+            block_node.location.start_offset,
+            block_node.location.end_offset,
+        );
+        if self.has_comments_between(start, end) {
+            return None;
+        }
+
+        let mut body = None;
+        for child in &block_node.children {
+            match child.node_type {
+                NodeType::StatementsNode => {
+                    let text = self
+                        .rewritten_source()
+                        .slice(child.location.start_offset, child.location.end_offset)?;
+                    if text.trim().contains('\n') {
+                        return None;
+                    }
+                    body = Some(text.trim().to_string());
+                }
+                NodeType::BeginNode => return None,
+                _ => {}
+            }
+        }
+
+        let parameters = self.block_parameters(block_node);
+        if parameters.as_deref().is_some_and(|p| p.contains('\n')) {
+            return None;
+        }
+
+        let call_text = self
+            .source
+            .get(call_node.location.start_offset..start)?
+            .trim_end();
+        if call_text.contains('\n') {
+            return None;
+        }
+
+        let inline = block::inline(parameters.as_deref(), body.as_deref());
+        let parens = if Self::has_parenless_arguments(call_node) {
+            2
+        } else {
+            0
+        };
+        let width =
+            self.current_column() + call_text.chars().count() + parens + 1 + inline.chars().count();
+
+        (width <= self.config.formatting.line_length).then_some(inline)
+    }
+
+    /// Whether the call has arguments written without parentheses, as in `foo bar`
+    fn has_parenless_arguments(call_node: &Node) -> bool {
+        call_node
+            .metadata
+            .get("has_parens")
+            .is_some_and(|v| v == "false")
+    }
+
+    /// Emit an operator expression statement such as `a && b` or `total + tax`
+    fn emit_operation(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        let Some(expression) = self.operation(node) else {
+            return self.emit_generic(node, indent_level);
+        };
+
+        self.emit_comments_before(node.location.start_line, indent_level)?;
+        self.emit_indent(indent_level)?;
+        self.write_operation(&expression, indent_level, true)?;
+        self.emit_trailing_comments(node.location.end_line)?;
+        Ok(())
+    }
+
+    /// Emit a variable or constant assignment
+    fn emit_assignment(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        match node.children.last() {
+            Some(value) => self.emit_with_value(node, value, indent_level),
+            None => self.emit_generic(node, indent_level),
+        }
+    }
+
+    /// Emit `return value`
+    fn emit_return(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        match node.children.as_slice() {
+            [value] => self.emit_with_value(node, value, indent_level),
+            _ => self.emit_generic(node, indent_level),
+        }
+    }
+
+    /// Emit a statement that ends with `value`, such as `total = a + b` or
+    /// `return User.where(...)`, formatting an operator expression or a call on the
+    /// right. Other values are copied verbatim.
+    fn emit_with_value(&mut self, node: &Node, value: &Node, indent_level: usize) -> Result<()> {
+        let is_call =
+            matches!(value.node_type, NodeType::CallNode) && !operator::is_operation(value);
+        let expression = match self.operation(value) {
+            Some(expression) => Some(expression),
+            None if is_call => None,
+            None => return self.emit_generic(node, indent_level),
+        };
+        if self.has_comments_between(node.location.start_offset, value.location.start_offset) {
+            return self.emit_generic(node, indent_level);
+        }
+
+        self.emit_comments_before(node.location.start_line, indent_level)?;
+        self.emit_indent(indent_level)?;
+        // `total = `, `total += ` or `return `, with a single space before the value.
+        // The target is copied so that `align_assignments` padding applies.
+        self.write_source(node.location.start_offset, value.location.start_offset)?;
+        self.buffer.truncate(self.buffer.trim_end().len());
+        self.buffer.push(' ');
+        match expression {
+            Some(expression) => self.write_operation(&expression, indent_level, true)?,
+            None => self.write_call(value, indent_level)?,
+        }
+        self.emit_trailing_comments(node.location.end_line)?;
+        Ok(())
+    }
+
+    /// Write a condition or other expression, formatting it when it is an operator
+    /// expression and copying it verbatim otherwise
+    fn write_expression(
+        &mut self,
+        node: &Node,
+        indent_level: usize,
+        breakable: bool,
+    ) -> Result<()> {
+        match self.operation(node) {
+            Some(expression) => self.write_operation(&expression, indent_level, breakable),
+            None => self.write_source_text(node),
+        }
+    }
+
+    /// Rebuild an operator expression, unless it has to be copied verbatim: when it
+    /// contains comments or heredocs, or an operand spans several lines
+    fn operation<'a>(&self, node: &'a Node) -> Option<Expression<'a>> {
+        if !operator::is_operation(node) {
+            return None;
+        }
+
+        let (start, end) = (node.location.start_offset, node.location.end_offset);
+        if self.has_comments_between(start, end)
+            || self
+                .heredocs
+                .iter()
+                .any(|heredoc| heredoc.opener >= start && heredoc.opener < end)
+        {
+            return None;
+        }
+
+        operator::parse(node, &self.rewritten_source())
+    }
+
+    /// Write an expression on one line, or, when `breakable` and it does not fit in
+    /// `line_length`, one operand per line after its lowest-precedence operators
+    fn write_operation(
+        &mut self,
+        expression: &Expression,
+        indent_level: usize,
+        breakable: bool,
+    ) -> Result<()> {
+        let flat = expression.render();
+        let fits =
+            self.current_column() + flat.chars().count() <= self.config.formatting.line_length;
+
+        match expression.split() {
+            Some((operands, operators)) if breakable && !fits => {
+                self.buffer.push_str(&operands[0]);
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    writeln!(self.buffer, " {}", operator)?;
+                    self.emit_indent(indent_level + 1)?;
+                    self.buffer.push_str(operand);
+                }
+            }
+            _ => self.buffer.push_str(&flat),
+        }
+        Ok(())
+    }
+
+    /// Whether an unemitted comment starts within `start..end` (byte offsets)
+    fn has_comments_between(&self, start: usize, end: usize) -> bool {
+        self.all_comments.iter().enumerate().any(|(idx, comment)| {
+            !self.emitted_comment_indices.contains(&idx)
+                && comment.location.start_offset >= start
+                && comment.location.start_offset < end
+        })
+    }
+
     /// Detect whether block uses do...end or { } style
     fn detect_block_style(&self, block_node: &Node) -> BlockStyle {
         if self.source.is_empty() {
@@ -1180,28 +2365,156 @@ impl Emitter {
         BlockStyle::DoEnd // Default (includes 'do' keyword)
     }
 
-    /// Emit the method call part without the block
-    fn emit_call_without_block(
+    /// Write the method call part without the block, optionally wrapping parenless
+    /// arguments in parentheses
+    fn write_call_without_block(
         &mut self,
         call_node: &Node,
         block_node: &Node,
         indent_level: usize,
+        add_parens: bool,
     ) -> Result<()> {
-        self.emit_indent(indent_level)?;
-
         if !self.source.is_empty() {
             let start = call_node.location.start_offset;
             let end = block_node.location.start_offset;
 
             if let Some(text) = self.source.get(start..end) {
                 // Trim trailing whitespace but preserve the content
-                write!(self.buffer, "{}", text.trim_end())?;
+                let trimmed_end = start + text.trim_end().len();
+
+                if add_parens {
+                    if let Some((args_start, args_end)) = Self::arguments_range(call_node) {
+                        let message_end = start + self.source[start..args_start].trim_end().len();
+                        self.write_source(start, message_end)?;
+                        self.buffer.push('(');
+                        self.write_source(args_start, args_end)?;
+                        write!(self.buffer, ")")?;
+                        return Ok(());
+                    }
+                }
+
+                if let Some(chain) = self.broken_chain(call_node, trimmed_end) {
+                    return self.emit_method_chain(&chain, indent_level);
+                }
+
+                self.write_source(start, trimmed_end)?;
             }
         }
+        Ok(())
+    }
+
+    /// Byte range of a call's arguments, from the first to the end of the last
+    fn arguments_range(call_node: &Node) -> Option<(usize, usize)> {
+        let skip = usize::from(call_node.metadata.contains_key("call_operator"));
+        let arguments = call_node
+            .children
+            .iter()
+            .skip(skip)
+            .filter(|c| !matches!(c.node_type, NodeType::BlockNode));
+
+        let mut range = None;
+        for argument in arguments {
+            let (start, _) =
+                range.get_or_insert((argument.location.start_offset, argument.location.end_offset));
+            range = Some((*start, argument.location.end_offset));
+        }
+        range
+    }
+
+    /// Flatten a call into a method chain if it should be laid out one call per line:
+    /// when it has at least two links and does not fit in `line_length`, or when the
+    /// source already broke it across lines. Other calls are copied verbatim.
+    ///
+    /// The chain would start at the current column.
+    fn broken_chain<'a>(&self, node: &'a Node, end: usize) -> Option<Chain<'a>> {
+        // Heredoc bodies sit between the links in the source; keep those chains as written
+        if self
+            .heredocs
+            .iter()
+            .any(|h| h.opener >= node.location.start_offset && h.opener < end)
+        {
+            return None;
+        }
+
+        let chain = chain::flatten(node, end, &self.rewritten_source())?;
+        if chain.is_broken() {
+            return Some(chain);
+        }
+
+        let root = &chain.root.location;
+        let width = self.current_column()
+            + self
+                .rewritten_source()
+                .slice(root.start_offset, root.end_offset)?
+                .chars()
+                .count()
+            + chain
+                .links
+                .iter()
+                .map(|link| link.text.chars().count())
+                .sum::<usize>();
+
+        (chain.links.len() >= 2 && width > self.config.formatting.line_length).then_some(chain)
+    }
+
+    /// Emit a method chain with leading-dot continuation lines indented one level deeper
+    ///
+    /// The first call stays on the root's line unless the source put it on its own line.
+    /// Comments between links are kept: trailing ones stay after their link, standalone
+    /// ones get their own continuation line.
+    fn emit_method_chain(&mut self, chain: &Chain, indent_level: usize) -> Result<()> {
+        let continuation = indent_level + 1;
+        let root = &chain.root.location;
+        self.write_source(root.start_offset, root.end_offset)?;
+
+        for (i, link) in chain.links.iter().enumerate() {
+            let (gap_start, gap_end) = link.gap;
+            let mut comment_indices: Vec<usize> = (0..self.all_comments.len())
+                .filter(|idx| {
+                    let offset = self.all_comments[*idx].location.start_offset;
+                    !self.emitted_comment_indices.contains(idx)
+                        && offset >= gap_start
+                        && offset < gap_end
+                })
+                .collect();
+            comment_indices.sort_by_key(|&idx| self.all_comments[idx].location.start_offset);
+
+            for idx in comment_indices {
+                let comment_start = self.all_comments[idx].location.start_offset;
+                if self.source[gap_start..comment_start].contains('\n') {
+                    self.buffer.push('\n');
+                    self.emit_indent(continuation)?;
+                    write!(self.buffer, "{}", self.comment_text(idx, continuation))?;
+                    self.emitted_comment_indices.insert(idx);
+                } else {
+                    self.write_trailing_comment(idx)?;
+                }
+            }
+
+            if i > 0 || chain.root_stands_alone() {
+                self.buffer.push('\n');
+                self.emit_indent(continuation)?;
+            }
+            write!(self.buffer, "{}", link.text)?;
+        }
 
         Ok(())
     }
 
+    /// Display width of the line currently being written, counting a tab as one indent
+    fn current_column(&self) -> usize {
+        let line = self.buffer.rsplit('\n').next().unwrap_or("");
+        line.chars()
+            .map(|c| {
+                if c == '\t' {
+                    self.config.formatting.indent_width
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
     /// Emit a do...end style block with proper indentation
     fn emit_do_end_block(&mut self, block_node: &Node, indent_level: usize) -> Result<()> {
         // Add space before 'do' and emit 'do'
@@ -1214,6 +2527,20 @@ impl Emitter {
         self.emit_trailing_comments(block_node.location.start_line)?;
         self.buffer.push('\n');
 
+        self.emit_block_body(block_node, indent_level)?;
+
+        // Emit 'end'
+        self.emit_indent(indent_level)?;
+        write!(self.buffer, "end")?;
+        // Emit trailing comments on end line
+        self.emit_trailing_comments(block_node.location.end_line)?;
+
+        Ok(())
+    }
+
+    /// Emit the statements of a multi-line block and the comments before its closing
+    /// `end` or `}`, leaving the buffer at the start of the closing line
+    fn emit_block_body(&mut self, block_node: &Node, indent_level: usize) -> Result<()> {
         // Find and emit the body (StatementsNode among children)
         let block_start_line = block_node.location.start_line;
         let block_end_line = block_node.location.end_line;
@@ -1263,35 +2590,25 @@ impl Emitter {
             self.buffer.push('\n');
         }
 
-        // Emit 'end'
-        self.emit_indent(indent_level)?;
-        write!(self.buffer, "end")?;
-        // Emit trailing comments on end line
-        self.emit_trailing_comments(block_end_line)?;
-
         Ok(())
     }
 
     /// Emit a { } style block
     fn emit_brace_block(&mut self, block_node: &Node, indent_level: usize) -> Result<()> {
-        // Determine if block should be inline or multiline
-        let is_multiline = block_node.location.start_line != block_node.location.end_line;
+        // Determine if block should be inline or multiline; a one-line do...end block
+        // turned into braces is laid out on several lines as well
+        let is_multiline = block_node.location.start_line != block_node.location.end_line
+            || self.detect_block_style(block_node) == BlockStyle::DoEnd;
         let block_end_line = block_node.location.end_line;
 
         if is_multiline {
             // Multiline brace block
             write!(self.buffer, " {{")?;
             self.emit_block_parameters(block_node)?;
+            self.emit_trailing_comments(block_node.location.start_line)?;
             self.buffer.push('\n');
 
-            // Emit body
-            for child in &block_node.children {
-                if matches!(child.node_type, NodeType::StatementsNode) {
-                    self.emit_statements(child, indent_level + 1)?;
-                    self.buffer.push('\n');
-                    break;
-                }
-            }
+            self.emit_block_body(block_node, indent_level)?;
 
             self.emit_indent(indent_level)?;
             write!(self.buffer, "}}")?;
@@ -1299,12 +2616,7 @@ impl Emitter {
         } else {
             // Inline brace block - extract from source to preserve spacing
             write!(self.buffer, " ")?;
-            if let Some(text) = self
-                .source
-                .get(block_node.location.start_offset..block_node.location.end_offset)
-            {
-                write!(self.buffer, "{}", text)?;
-            }
+            self.write_source_text(block_node)?;
             self.emit_trailing_comments(block_end_line)?;
         }
 
@@ -1313,27 +2625,94 @@ impl Emitter {
 
     /// Emit block parameters (|x, y|)
     fn emit_block_parameters(&mut self, block_node: &Node) -> Result<()> {
-        if self.source.is_empty() {
+        let Some(params) = self.block_parameters(block_node) else {
             return Ok(());
+        };
+        write!(self.buffer, " {}", params)?;
+
+        // Comments inside parameters copied as written are part of the text
+        if let Some(node) = Self::block_parameters_node(block_node) {
+            let (start, end) = (node.location.start_offset, node.location.end_offset);
+            for (idx, comment) in self.all_comments.iter().enumerate() {
+                if comment.location.start_offset >= start && comment.location.start_offset < end {
+                    self.emitted_comment_indices.insert(idx);
+                }
+            }
         }
 
-        let start = block_node.location.start_offset;
-        let end = block_node.location.end_offset;
-
-        if let Some(block_source) = self.source.get(start..end) {
-            // Only look at the first line of the block for parameters
-            let first_line = block_source.lines().next().unwrap_or("");
-
-            // Find |...| pattern in the first line only
-            if let Some(pipe_start) = first_line.find('|') {
-                // Find matching pipe after first one
-                if let Some(pipe_end) = first_line[pipe_start + 1..].find('|') {
-                    let params = &first_line[pipe_start..=pipe_start + 1 + pipe_end];
-                    write!(self.buffer, " {}", params)?;
-                }
+        Ok(())
+    }
+
+    /// Block parameters rebuilt from the parameter nodes (`|a, (b, c); d|`), or as
+    /// written when they contain comments or a multi-line default. `None` when the
+    /// block has no parameters or uses implicit ones (`_1`, `it`).
+    fn block_parameters(&self, block_node: &Node) -> Option<String> {
+        let node = Self::block_parameters_node(block_node)?;
+        let (start, end) = (node.location.start_offset, node.location.end_offset);
+
+        if !self.has_comments_between(start, end) {
+            if let Some(params) =
+                parameters::format_block_parameters(node, &self.rewritten_source())
+            {
+                return Some(params);
             }
         }
 
+        self.source.get(start..end).map(str::to_string)
+    }
+
+    /// The `|...|` parameters node of a block
+    fn block_parameters_node(block_node: &Node) -> Option<&Node> {
+        block_node
+            .children
+            .iter()
+            .find(|c| matches!(c.node_type, NodeType::BlockParametersNode))
+    }
+
+    /// Copy a node's source, marking the comments on its inner lines as emitted since
+    /// they are part of the copied text. Comments on the last line come after the node
+    /// and are left for `emit_trailing_comments`.
+    ///
+    /// Copied comments that follow code are recorded for `align_trailing_comments`,
+    /// with the space before them reduced to one when that option is on.
+    fn write_node_source(&mut self, node: &Node) -> Result<()> {
+        let mut inner: Vec<usize> = (0..self.all_comments.len())
+            .filter(|&idx| {
+                let location = &self.all_comments[idx].location;
+                !self.emitted_comment_indices.contains(&idx)
+                    && location.start_line >= node.location.start_line
+                    && location.end_line < node.location.end_line
+            })
+            .collect();
+        inner.sort_by_key(|&idx| self.all_comments[idx].location.start_offset);
+
+        self.copied_comments = inner
+            .iter()
+            .map(|&idx| (self.all_comments[idx].location.start_offset, None))
+            .collect();
+        self.write_source_text(node)?;
+        let copied = std::mem::take(&mut self.copied_comments);
+
+        // Bytes the buffer has shrunk by so far from setting the space before a comment
+        let mut shrunk = 0isize;
+        for (idx, (_, at)) in inner.into_iter().zip(copied) {
+            self.emitted_comment_indices.insert(idx);
+            let Some(at) = at.map(|at| at.saturating_add_signed(-shrunk)) else {
+                continue;
+            };
+            let line_start = self.buffer[..at].rfind('\n').map_or(0, |i| i + 1);
+            let code = self.buffer[line_start..at].trim_end();
+            if code.trim_start().is_empty() {
+                continue;
+            }
+
+            let code_end = line_start + code.len();
+            if self.config.formatting.comments.align_trailing_comments {
+                self.buffer.replace_range(code_end..at, " ");
+                shrunk += (at - code_end) as isize - 1;
+            }
+            self.trailing_comments.push(code_end);
+        }
         Ok(())
     }
 
@@ -1343,23 +2722,9 @@ impl Emitter {
             let start = node.location.start_offset;
             let end = node.location.end_offset;
 
-            let text_owned = self.source.get(start..end).map(|s| s.to_string());
-
-            if let Some(text) = text_owned {
+            if self.source.get(start..end).is_some() {
                 self.emit_indent(indent_level)?;
-                write!(self.buffer, "{}", text)?;
-
-                // Mark comments that are strictly inside this node's line range as emitted
-                // (they are included in the source extraction)
-                // Don't mark trailing comments on the last line (they come after the node ends)
-                for (idx, comment) in self.all_comments.iter().enumerate() {
-                    if !self.emitted_comment_indices.contains(&idx)
-                        && comment.location.start_line >= node.location.start_line
-                        && comment.location.end_line < node.location.end_line
-                    {
-                        self.emitted_comment_indices.insert(idx);
-                    }
-                }
+                self.write_node_source(node)?;
 
                 // Emit trailing comments on the same line (after the node ends)
                 self.emit_trailing_comments(node.location.end_line)?;
@@ -1376,23 +2741,9 @@ impl Emitter {
             let start = node.location.start_offset;
             let end = node.location.end_offset;
 
-            let text_owned = self.source.get(start..end).map(|s| s.to_string());
-
-            if let Some(text) = text_owned {
+            if self.source.get(start..end).is_some() {
                 self.emit_indent(indent_level)?;
-                write!(self.buffer, "{}", text)?;
-
-                // Mark comments that are strictly inside this node's line range as emitted
-                // (they are included in the source extraction)
-                // Don't mark trailing comments on the last line (they come after the node ends)
-                for (idx, comment) in self.all_comments.iter().enumerate() {
-                    if !self.emitted_comment_indices.contains(&idx)
-                        && comment.location.start_line >= node.location.start_line
-                        && comment.location.end_line < node.location.end_line
-                    {
-                        self.emitted_comment_indices.insert(idx);
-                    }
-                }
+                self.write_node_source(node)?;
 
                 self.emit_trailing_comments(node.location.end_line)?;
             }
@@ -1443,15 +2794,10 @@ impl Emitter {
         self.emit_indent(indent_level)?;
         write!(self.buffer, "{} ", keyword)?;
 
-        // Emit predicate (condition) - first child
+        // Emit predicate (condition) - first child. Its continuation lines go one
+        // level deeper than the body so that the two stay apart.
         if let Some(predicate) = node.children.first() {
-            if !self.source.is_empty() {
-                let start = predicate.location.start_offset;
-                let end = predicate.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, "{}", text)?;
-                }
-            }
+            self.write_expression(predicate, indent_level + 1, true)?;
         }
 
         // Emit trailing comment on same line as while/until
@@ -1487,26 +2833,14 @@ impl Emitter {
 
         // Emit index variable - first child
         if let Some(index) = node.children.first() {
-            if !self.source.is_empty() {
-                let start = index.location.start_offset;
-                let end = index.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, "{}", text)?;
-                }
-            }
+            self.write_source_text(index)?;
         }
 
         write!(self.buffer, " in ")?;
 
         // Emit collection - second child
         if let Some(collection) = node.children.get(1) {
-            if !self.source.is_empty() {
-                let start = collection.location.start_offset;
-                let end = collection.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, "{}", text)?;
-                }
-            }
+            self.write_source_text(collection)?;
         }
 
         self.buffer.push('\n');
@@ -1536,13 +2870,7 @@ impl Emitter {
 
         // First child is the expression (self or an object)
         if let Some(expression) = node.children.first() {
-            if !self.source.is_empty() {
-                let start = expression.location.start_offset;
-                let end = expression.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, "{}", text)?;
-                }
-            }
+            self.write_source_text(expression)?;
         }
 
         // Emit trailing comments on the class << line
@@ -1561,7 +2889,7 @@ impl Emitter {
             }
             if matches!(child.node_type, NodeType::StatementsNode) {
                 has_body_content = true;
-                self.emit_statements(child, indent_level + 1)?;
+                self.emit_body_statements(child, indent_level + 1, node)?;
             } else if !self.is_structural_node(&child.node_type) {
                 has_body_content = true;
                 self.emit_node(child, indent_level + 1)?;
@@ -1569,7 +2897,8 @@ impl Emitter {
         }
 
         // Emit comments that appear before the end statement while preserving their position
-        self.emit_comments_before_end(class_start_line, class_end_line, indent_level + 1)?;
+        let level = self.end_comments_level(node, indent_level + 1);
+        self.emit_comments_before_end(class_start_line, class_end_line, level)?;
 
         // Add newline before end if there was body content
         if (has_body_content || self.has_comments_in_range(class_start_line + 1, class_end_line))
@@ -1598,11 +2927,8 @@ impl Emitter {
         if let Some(first_child) = node.children.first() {
             if !matches!(first_child.node_type, NodeType::InNode | NodeType::ElseNode) {
                 // This is the predicate - extract from source
-                let start = first_child.location.start_offset;
-                let end = first_child.location.end_offset;
-                if let Some(text) = self.source.get(start..end) {
-                    write!(self.buffer, " {}", text)?;
-                }
+                write!(self.buffer, " ")?;
+                self.write_source_text(first_child)?;
                 in_start_idx = 1;
             }
         }
@@ -1610,27 +2936,7 @@ impl Emitter {
         self.buffer.push('\n');
 
         // Emit in clauses and else
-        for child in node.children.iter().skip(in_start_idx) {
-            match &child.node_type {
-                NodeType::InNode => {
-                    self.emit_in(child, indent_level)?;
-                }
-                NodeType::ElseNode => {
-                    self.emit_indent(indent_level)?;
-                    writeln!(self.buffer, "else")?;
-                    // Emit else body
-                    for else_child in &child.children {
-                        if matches!(else_child.node_type, NodeType::StatementsNode) {
-                            self.emit_statements(else_child, indent_level + 1)?;
-                        } else {
-                            self.emit_node(else_child, indent_level + 1)?;
-                        }
-                    }
-                    self.buffer.push('\n');
-                }
-                _ => {}
-            }
-        }
+        self.emit_case_branches(&node.children[in_start_idx..], indent_level)?;
 
         // Emit "end" keyword
         self.emit_indent(indent_level)?;
@@ -1640,7 +2946,7 @@ impl Emitter {
         Ok(())
     }
 
-    /// Emit in node (pattern matching clause)
+    /// Emit an `in` branch (pattern matching clause) with its body on the following lines
     fn emit_in(&mut self, node: &Node, indent_level: usize) -> Result<()> {
         self.emit_comments_before(node.location.start_line, indent_level)?;
         self.emit_indent(indent_level)?;
@@ -1649,31 +2955,127 @@ impl Emitter {
 
         // First child is the pattern
         if let Some(pattern) = node.children.first() {
-            self.write_source_text(pattern)?;
+            self.write_pattern(pattern, indent_level)?;
+            self.emit_trailing_comments(pattern.location.end_line)?;
         }
 
-        if self.is_single_line(node) {
-            // Inline style: in X then Y
-            if let Some(statements) = node.children.get(1) {
-                write!(self.buffer, " then ")?;
-                self.write_source_text(statements)?;
+        // Second child is the statements body
+        if let Some(statements) = node.children.get(1) {
+            if matches!(statements.node_type, NodeType::StatementsNode) {
+                self.buffer.push('\n');
+                self.emit_statements(statements, indent_level + 1)?;
             }
-            self.buffer.push('\n');
-        } else {
-            // Multi-line style: in X\n  Y
-            self.buffer.push('\n');
+        }
 
-            // Second child is the statements body
-            if let Some(statements) = node.children.get(1) {
-                if matches!(statements.node_type, NodeType::StatementsNode) {
-                    self.emit_statements(statements, indent_level + 1)?;
-                }
+        Ok(())
+    }
+
+    /// Emit a one-line pattern match: `config => { db: }` or `value in [Integer, *]`
+    fn emit_pattern_match(
+        &mut self,
+        node: &Node,
+        indent_level: usize,
+        operator: &str,
+    ) -> Result<()> {
+        let [value, pattern] = node.children.as_slice() else {
+            return self.emit_generic(node, indent_level);
+        };
+        if self.has_comments_between(node.location.start_offset, node.location.end_offset) {
+            return self.emit_generic(node, indent_level);
+        }
+
+        self.emit_comments_before(node.location.start_line, indent_level)?;
+        self.emit_indent(indent_level)?;
+        self.write_expression(value, indent_level, false)?;
+        write!(self.buffer, " {} ", operator)?;
+        self.write_pattern(pattern, indent_level)?;
+        self.emit_trailing_comments(node.location.end_line)?;
+        Ok(())
+    }
+
+    /// Write a pattern with normalized spacing, breaking a wide array or hash pattern
+    /// into one element per line. Patterns with comments or multi-line parts are copied
+    /// as written.
+    fn write_pattern(&mut self, node: &Node, indent_level: usize) -> Result<()> {
+        let Some(flat) = self.pattern_text(node) else {
+            return self.write_source_text(node);
+        };
+
+        let (pattern, guard) = Self::pattern_guard(node);
+        let fits =
+            self.current_column() + flat.chars().count() <= self.config.formatting.line_length;
+        let bracketed = pattern::bracketed(pattern, &self.rewritten_source())
+            .filter(|bracketed| !fits && !bracketed.elements.is_empty());
+
+        let Some(bracketed) = bracketed else {
+            self.buffer.push_str(&flat);
+            return Ok(());
+        };
+
+        self.buffer.push_str(&bracketed.open);
+        let last = bracketed.elements.len() - 1;
+        for (i, element) in bracketed.elements.iter().enumerate() {
+            self.buffer.push('\n');
+            self.emit_indent(indent_level + 1)?;
+            self.buffer.push_str(element);
+            if i < last {
+                self.buffer.push(',');
             }
         }
+        self.buffer.push('\n');
+        self.emit_indent(indent_level)?;
+        self.buffer.push_str(bracketed.close);
 
+        if let Some((keyword, predicate)) = guard {
+            write!(self.buffer, " {} ", keyword)?;
+            self.write_source_text(predicate)?;
+        }
         Ok(())
     }
 
+    /// A pattern and its guard on one line (`[x, y] if x > y`), unless it contains
+    /// comments or spans several lines
+    fn pattern_text(&self, node: &Node) -> Option<String> {
+        if self.has_comments_between(node.location.start_offset, node.location.end_offset) {
+            return None;
+        }
+
+        let (pattern, guard) = Self::pattern_guard(node);
+        let mut text = pattern::render(pattern, &self.rewritten_source())?;
+        if let Some((keyword, predicate)) = guard {
+            let predicate = self
+                .source
+                .get(predicate.location.start_offset..predicate.location.end_offset)?;
+            if predicate.contains('\n') {
+                return None;
+            }
+            text = format!("{} {} {}", text, keyword, predicate);
+        }
+        Some(text)
+    }
+
+    /// Split `in pattern if guard` into the pattern and its `if`/`unless` guard; the
+    /// parser wraps such patterns in a modifier IfNode/UnlessNode
+    fn pattern_guard(node: &Node) -> (&Node, Option<(&'static str, &Node)>) {
+        let keyword = match node.node_type {
+            NodeType::IfNode => "if",
+            NodeType::UnlessNode => "unless",
+            _ => return (node, None),
+        };
+
+        match node.children.as_slice() {
+            [predicate, statements, ..]
+                if statements.location.start_offset < predicate.location.start_offset =>
+            {
+                match statements.children.as_slice() {
+                    [pattern] => (pattern, Some((keyword, predicate))),
+                    _ => (node, None),
+                }
+            }
+            _ => (node, None),
+        }
+    }
+
     /// Check if node is structural (part of definition syntax, not body)
     /// These nodes are part of class/module/method definitions and should not be emitted as body
     fn is_structural_node(&self, node_type: &NodeType) -> bool {
@@ -1692,6 +3094,7 @@ impl Emitter {
                 | NodeType::BlockParameterNode
                 | NodeType::ForwardingParameterNode
                 | NodeType::NoKeywordsParameterNode
+                | NodeType::MultiTargetNode
         )
     }
 }
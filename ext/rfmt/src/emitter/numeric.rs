@@ -0,0 +1,120 @@
+use crate::config::{LetterCase, NumericLiteralsConfig};
+
+/// Normalize an integer or float literal such as `1000000`, `0XFF` or `1.5E3`
+///
+/// Decimal integers (and the integer part of floats) with at least
+/// `underscore_min_digits` digits are grouped in threes, the `0x`/`0b`/`0o`/`0d`
+/// prefix, hex digits and exponent marker get the configured case. Legacy octal
+/// literals (`0755`) are kept as written.
+pub(super) fn normalize(text: &str, config: &NumericLiteralsConfig) -> String {
+    let (sign, number) = match text.strip_prefix(['-', '+']) {
+        Some(rest) => text.split_at(text.len() - rest.len()),
+        None => ("", text),
+    };
+
+    let mut chars = number.chars();
+    let (first, second) = (chars.next(), chars.next());
+    if first == Some('0') && second.is_some_and(|c| c.is_ascii_alphabetic()) {
+        let (prefix, digits) = number.split_at(2);
+        let digits = if prefix.eq_ignore_ascii_case("0x") {
+            apply_case(digits, &config.hex_digits)
+        } else {
+            digits.to_string()
+        };
+        return format!("{}{}{}", sign, apply_case(prefix, &config.prefix), digits);
+    }
+    if first == Some('0') && second.is_some_and(|c| c.is_ascii_digit() || c == '_') {
+        return text.to_string();
+    }
+
+    let split = number.find(['.', 'e', 'E']).unwrap_or(number.len());
+    let (integer, rest) = number.split_at(split);
+    let integer = match config.underscore_min_digits {
+        Some(min_digits) => group_digits(integer, min_digits),
+        None => integer.to_string(),
+    };
+    let rest = match config.exponent {
+        LetterCase::Preserve => rest.to_string(),
+        LetterCase::Lower => rest.replace('E', "e"),
+        LetterCase::Upper => rest.replace('e', "E"),
+    };
+    format!("{}{}{}", sign, integer, rest)
+}
+
+fn apply_case(text: &str, case: &LetterCase) -> String {
+    match case {
+        LetterCase::Preserve => text.to_string(),
+        LetterCase::Lower => text.to_ascii_lowercase(),
+        LetterCase::Upper => text.to_ascii_uppercase(),
+    }
+}
+
+/// `1000000` becomes `1_000_000`; numbers shorter than `min_digits` are kept as written
+fn group_digits(integer: &str, min_digits: usize) -> String {
+    let digits: Vec<char> = integer.chars().filter(|&c| c != '_').collect();
+    if digits.len() < min_digits {
+        return integer.to_string();
+    }
+
+    let head = match digits.len() % 3 {
+        0 => 3.min(digits.len()),
+        n => n,
+    };
+    let mut grouped: String = digits[..head].iter().collect();
+    for group in digits[head..].chunks(3) {
+        grouped.push('_');
+        grouped.extend(group);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(min_digits: Option<usize>, case: LetterCase) -> NumericLiteralsConfig {
+        NumericLiteralsConfig {
+            underscore_min_digits: min_digits,
+            hex_digits: case.clone(),
+            prefix: LetterCase::Lower,
+            exponent: case,
+        }
+    }
+
+    #[test]
+    fn test_group_decimal_digits() {
+        let config = config(Some(5), LetterCase::Preserve);
+
+        assert_eq!(normalize("1000000", &config), "1_000_000");
+        assert_eq!(normalize("-10_00_000", &config), "-1_000_000");
+        assert_eq!(normalize("1234567.891", &config), "1_234_567.891");
+        assert_eq!(normalize("1000", &config), "1000");
+        assert_eq!(normalize("10_00", &config), "10_00");
+        assert_eq!(normalize("0755", &config), "0755");
+    }
+
+    #[test]
+    fn test_prefix_and_hex_case() {
+        let config = config(None, LetterCase::Upper);
+
+        assert_eq!(normalize("0xdead_beef", &config), "0xDEAD_BEEF");
+        assert_eq!(normalize("0B1010", &config), "0b1010");
+        assert_eq!(normalize("0O17", &config), "0o17");
+    }
+
+    #[test]
+    fn test_exponent_case() {
+        assert_eq!(
+            normalize("1.5E3", &config(None, LetterCase::Lower)),
+            "1.5e3"
+        );
+        assert_eq!(
+            normalize("2e-10", &config(None, LetterCase::Upper)),
+            "2E-10"
+        );
+        assert_eq!(
+            normalize("2e-10", &config(None, LetterCase::Preserve)),
+            "2e-10"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use super::literal::Source;
+use crate::ast::{Node, NodeType};
+
+/// An operator expression rebuilt from the AST, such as `a + b * c` or `!ready?`
+///
+/// Operands that are not operators themselves are kept as written.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Expression<'a> {
+    Operand(String),
+    Parenthesized(Box<Expression<'a>>),
+    Unary {
+        operator: &'a str,
+        operand: Box<Expression<'a>>,
+    },
+    Binary {
+        operator: &'a str,
+        left: Box<Expression<'a>>,
+        right: Box<Expression<'a>>,
+    },
+}
+
+/// Whether `node` is an operator expression: `&&`/`and`, `||`/`or`, or a binary or
+/// prefix operator call
+pub(super) fn is_operation(node: &Node) -> bool {
+    match node.node_type {
+        NodeType::AndNode | NodeType::OrNode => node.children.len() == 2,
+        NodeType::CallNode => {
+            node.metadata.contains_key("binary_operator")
+                || node.metadata.contains_key("unary_operator")
+        }
+        _ => false,
+    }
+}
+
+/// Rebuild an expression from `node`
+///
+/// Returns `None` when an operand spans several lines and cannot be moved around.
+pub(super) fn parse<'a>(node: &'a Node, source: &Source) -> Option<Expression<'a>> {
+    let binary = |operator: &'a str| -> Option<Expression<'a>> {
+        match node.children.as_slice() {
+            [left, right] => Some(Expression::Binary {
+                operator,
+                left: Box::new(parse(left, source)?),
+                right: Box::new(parse(right, source)?),
+            }),
+            _ => None,
+        }
+    };
+
+    match node.node_type {
+        NodeType::AndNode => binary(operator_of(node, "&&")),
+        NodeType::OrNode => binary(operator_of(node, "||")),
+        NodeType::CallNode if node.metadata.contains_key("binary_operator") => {
+            binary(operator_of(node, ""))
+        }
+        NodeType::CallNode if node.metadata.contains_key("unary_operator") => {
+            let operator = node.metadata["unary_operator"].as_str();
+            let [operand] = node.children.as_slice() else {
+                return None;
+            };
+            let operand = parse(operand, source)?;
+            // `!` in front of `~x` would read as `!~`, so such pairs stay as written
+            if !operator.starts_with(char::is_alphabetic) && !can_follow_prefix(&operand.render()) {
+                return None;
+            }
+            Some(Expression::Unary {
+                operator,
+                operand: Box::new(operand),
+            })
+        }
+        NodeType::ParenthesesNode => match parenthesized_body(node) {
+            Some(inner) => Some(Expression::Parenthesized(Box::new(parse(inner, source)?))),
+            None => operand(node, source),
+        },
+        _ => operand(node, source),
+    }
+}
+
+impl Expression<'_> {
+    /// Render on one line: binary operators get one space on each side, while prefix
+    /// operators and `**` stay tight
+    pub fn render(&self) -> String {
+        match self {
+            Expression::Operand(text) => text.clone(),
+            Expression::Parenthesized(inner) => format!("({})", inner.render()),
+            Expression::Unary { operator, operand }
+                if operator.starts_with(char::is_alphabetic) =>
+            {
+                format!("{} {}", operator, operand.render())
+            }
+            Expression::Unary { operator, operand } => format!("{}{}", operator, operand.render()),
+            Expression::Binary {
+                operator: "**",
+                left,
+                right,
+            } => format!("{}**{}", left.render(), right.render()),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => format!("{} {} {}", left.render(), operator, right.render()),
+        }
+    }
+
+    /// Split at the lowest-precedence operator: the operands and the operators between
+    /// them, e.g. `a && b || c && d` becomes `[a && b, c && d]` and `["||"]`
+    ///
+    /// Returns `None` for expressions that should not be broken, such as `a**b`.
+    pub fn split(&self) -> Option<(Vec<String>, Vec<&str>)> {
+        let Expression::Binary { operator, .. } = self else {
+            return None;
+        };
+        if *operator == "**" {
+            return None;
+        }
+
+        let level = precedence(operator);
+        let mut operands = Vec::new();
+        let mut operators = Vec::new();
+        self.collect_level(level, &mut operands, &mut operators);
+        Some((operands, operators))
+    }
+
+    /// Walk the operators at `level`, collecting everything below them as operands
+    fn collect_level<'s>(
+        &'s self,
+        level: u8,
+        operands: &mut Vec<String>,
+        operators: &mut Vec<&'s str>,
+    ) {
+        match self {
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } if precedence(operator) == level => {
+                left.collect_level(level, operands, operators);
+                operators.push(operator);
+                right.collect_level(level, operands, operators);
+            }
+            _ => operands.push(self.render()),
+        }
+    }
+}
+
+/// Binding strength of a binary operator; higher binds tighter
+fn precedence(operator: &str) -> u8 {
+    match operator {
+        "**" => 12,
+        "*" | "/" | "%" => 11,
+        "+" | "-" => 10,
+        "<<" | ">>" => 9,
+        "&" => 8,
+        "|" | "^" => 7,
+        "<" | "<=" | ">" | ">=" => 6,
+        "==" | "!=" | "===" | "=~" | "!~" | "<=>" => 5,
+        "&&" => 4,
+        "||" => 3,
+        "and" | "or" => 1,
+        _ => 0,
+    }
+}
+
+fn operator_of<'a>(node: &'a Node, default: &'a str) -> &'a str {
+    node.metadata
+        .get("operator")
+        .or_else(|| node.metadata.get("binary_operator"))
+        .map_or(default, String::as_str)
+}
+
+/// The single statement inside `( ... )`, if that is all it holds
+fn parenthesized_body(node: &Node) -> Option<&Node> {
+    let [body] = node.children.as_slice() else {
+        return None;
+    };
+    match (&body.node_type, body.children.as_slice()) {
+        (NodeType::StatementsNode, [statement]) => Some(statement),
+        _ => None,
+    }
+}
+
+/// Whether `text` can directly follow a prefix operator without the two merging into
+/// another token
+fn can_follow_prefix(text: &str) -> bool {
+    text.starts_with(|c: char| {
+        c.is_alphanumeric() || matches!(c, '(' | '[' | '@' | '$' | ':' | '"' | '\'' | '_')
+    })
+}
+
+/// A node kept as written, if it fits on one line
+fn operand<'a>(node: &Node, source: &Source) -> Option<Expression<'a>> {
+    source
+        .line(node)
+        .map(|text| Expression::Operand(text.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn node(node_type: NodeType, start: usize, end: usize) -> Node {
+        Node::new(node_type, Location::new(1, start, 1, end, start, end))
+    }
+
+    fn with(mut node: Node, key: &str, value: &str, children: Vec<Node>) -> Node {
+        node.metadata.insert(key.to_string(), value.to_string());
+        node.with_children(children)
+    }
+
+    #[test]
+    fn test_render_normalizes_spacing() {
+        // a+b  *c
+        let source = &Source::plain("a+b  *c");
+        let product = with(
+            node(NodeType::CallNode, 2, 7),
+            "binary_operator",
+            "*",
+            vec![
+                node(NodeType::LocalVariableReadNode, 2, 3),
+                node(NodeType::LocalVariableReadNode, 6, 7),
+            ],
+        );
+        let sum = with(
+            node(NodeType::CallNode, 0, 7),
+            "binary_operator",
+            "+",
+            vec![node(NodeType::LocalVariableReadNode, 0, 1), product],
+        );
+
+        let expression = parse(&sum, source).unwrap();
+
+        assert_eq!(expression.render(), "a + b * c");
+    }
+
+    #[test]
+    fn test_render_keeps_unary_and_power_tight() {
+        // not x and -y ** 2
+        let source = &Source::plain("not x and -y ** 2");
+        let not = with(
+            node(NodeType::CallNode, 0, 5),
+            "unary_operator",
+            "not",
+            vec![node(NodeType::LocalVariableReadNode, 4, 5)],
+        );
+        let power = with(
+            node(NodeType::CallNode, 11, 17),
+            "binary_operator",
+            "**",
+            vec![
+                node(NodeType::LocalVariableReadNode, 11, 12),
+                node(NodeType::IntegerNode, 16, 17),
+            ],
+        );
+        let negate = with(
+            node(NodeType::CallNode, 10, 17),
+            "unary_operator",
+            "-",
+            vec![power],
+        );
+        let and = with(
+            node(NodeType::AndNode, 0, 17),
+            "operator",
+            "and",
+            vec![not, negate],
+        );
+
+        let expression = parse(&and, source).unwrap();
+
+        assert_eq!(expression.render(), "not x and -y**2");
+    }
+
+    #[test]
+    fn test_render_parenthesized() {
+        // ( a||b )&&c
+        let source = &Source::plain("( a||b )&&c");
+        let or = with(
+            node(NodeType::OrNode, 2, 6),
+            "operator",
+            "||",
+            vec![
+                node(NodeType::LocalVariableReadNode, 2, 3),
+                node(NodeType::LocalVariableReadNode, 5, 6),
+            ],
+        );
+        let parens = node(NodeType::ParenthesesNode, 0, 8).with_children(vec![node(
+            NodeType::StatementsNode,
+            2,
+            6,
+        )
+        .with_children(vec![or])]);
+        let and = with(
+            node(NodeType::AndNode, 0, 11),
+            "operator",
+            "&&",
+            vec![parens, node(NodeType::LocalVariableReadNode, 10, 11)],
+        );
+
+        assert_eq!(parse(&and, source).unwrap().render(), "(a || b) && c");
+    }
+
+    #[test]
+    fn test_split_at_lowest_precedence() {
+        let operand = |text: &str| Box::new(Expression::Operand(text.to_string()));
+        // a && b || c && d || e
+        let expression = Expression::Binary {
+            operator: "||",
+            left: Box::new(Expression::Binary {
+                operator: "||",
+                left: Box::new(Expression::Binary {
+                    operator: "&&",
+                    left: operand("a"),
+                    right: operand("b"),
+                }),
+                right: Box::new(Expression::Binary {
+                    operator: "&&",
+                    left: operand("c"),
+                    right: operand("d"),
+                }),
+            }),
+            right: operand("e"),
+        };
+
+        let (operands, operators) = expression.split().unwrap();
+
+        assert_eq!(operands, vec!["a && b", "c && d", "e"]);
+        assert_eq!(operators, vec!["||", "||"]);
+    }
+
+    #[test]
+    fn test_split_refuses_power_and_operands() {
+        let operand = |text: &str| Box::new(Expression::Operand(text.to_string()));
+        let power = Expression::Binary {
+            operator: "**",
+            left: operand("a"),
+            right: operand("b"),
+        };
+
+        assert_eq!(power.split(), None);
+        assert_eq!(Expression::Operand("a".to_string()).split(), None);
+    }
+
+    #[test]
+    fn test_prefix_operators_do_not_merge() {
+        // ! ~flags
+        let source = &Source::plain("! ~flags");
+        let complement = with(
+            node(NodeType::CallNode, 2, 8),
+            "unary_operator",
+            "~",
+            vec![node(NodeType::LocalVariableReadNode, 3, 8)],
+        );
+        let not = with(
+            node(NodeType::CallNode, 0, 8),
+            "unary_operator",
+            "!",
+            vec![complement],
+        );
+
+        assert_eq!(parse(&not, source), None);
+    }
+
+    #[test]
+    fn test_multiline_operand_is_not_rebuilt() {
+        let source = &Source::plain("a + [\n  1\n]");
+        let sum = with(
+            node(NodeType::CallNode, 0, 11),
+            "binary_operator",
+            "+",
+            vec![
+                node(NodeType::LocalVariableReadNode, 0, 1),
+                node(NodeType::ArrayNode, 4, 11),
+            ],
+        );
+
+        assert_eq!(parse(&sum, source), None);
+    }
+}
@@ -0,0 +1,204 @@
+use super::literal::Source;
+use crate::ast::{Node, NodeType};
+
+/// Render a parameter node in canonical form: `a`, `b = 1`, `*rest`, `c:`, `d: 2`,
+/// `**opts`, `**nil`, `&block`, `...` or `(a, *b)`
+///
+/// Returns `None` when the parameter cannot be rebuilt on one line, e.g. a default
+/// value spanning several lines.
+pub(super) fn format_parameter(node: &Node, source: &Source) -> Option<String> {
+    let name = node.metadata.get("name").map(String::as_str).unwrap_or("");
+    let default_value = || source.line(node.children.first()?);
+
+    let text = match node.node_type {
+        NodeType::RequiredParameterNode if !name.is_empty() => name.to_string(),
+        NodeType::OptionalParameterNode => format!("{} = {}", name, default_value()?),
+        NodeType::RestParameterNode => format!("*{}", name),
+        NodeType::RequiredKeywordParameterNode => format!("{}:", name),
+        NodeType::OptionalKeywordParameterNode => format!("{}: {}", name, default_value()?),
+        NodeType::KeywordRestParameterNode => format!("**{}", name),
+        NodeType::NoKeywordsParameterNode => "**nil".to_string(),
+        NodeType::BlockParameterNode => format!("&{}", name),
+        NodeType::ForwardingParameterNode => "...".to_string(),
+        NodeType::BlockLocalVariableNode => name.to_string(),
+        // Destructuring: `(a, (b, *c))`
+        NodeType::MultiTargetNode => format!("({})", format_list(&node.children, source)?),
+        NodeType::SplatNode => match node.children.first() {
+            Some(target) => format!("*{}", format_parameter(target, source)?),
+            None => "*".to_string(),
+        },
+        // Anything else is kept as written
+        _ => source.line(node)?.into_owned(),
+    };
+
+    Some(text)
+}
+
+/// Render block parameters from a BlockParametersNode: `|a, (b, c)|`, `|a,|`,
+/// `|x; y|` or `||`
+pub(super) fn format_block_parameters(node: &Node, source: &Source) -> Option<String> {
+    let (locals, params): (Vec<&Node>, Vec<&Node>) = node
+        .children
+        .iter()
+        .partition(|c| matches!(c.node_type, NodeType::BlockLocalVariableNode));
+
+    let mut text = format!("|{}", format_list(params, source)?);
+    if !locals.is_empty() {
+        let locals = locals
+            .into_iter()
+            .map(|local| format_parameter(local, source))
+            .collect::<Option<Vec<_>>>()?;
+        text.push_str("; ");
+        text.push_str(&locals.join(", "));
+    }
+    text.push('|');
+
+    Some(text)
+}
+
+/// Join parameters with `, `; an implicit rest (`|a,|`) keeps its trailing comma,
+/// which makes the block destructure its first argument
+fn format_list<'a>(params: impl IntoIterator<Item = &'a Node>, source: &Source) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut trailing_comma = false;
+    for param in params {
+        if matches!(param.node_type, NodeType::ImplicitRestNode) {
+            trailing_comma = true;
+        } else {
+            parts.push(format_parameter(param, source)?);
+        }
+    }
+
+    let mut text = parts.join(", ");
+    if trailing_comma {
+        text.push(',');
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn parameter(node_type: NodeType, name: Option<&str>, start: usize, end: usize) -> Node {
+        let mut node = Node::new(node_type, Location::new(1, start, 1, end, start, end));
+        if let Some(name) = name {
+            node.metadata.insert("name".to_string(), name.to_string());
+        }
+        node
+    }
+
+    #[test]
+    fn test_format_simple_parameters() {
+        let cases = [
+            (NodeType::RequiredParameterNode, Some("a"), "a"),
+            (NodeType::RestParameterNode, Some("args"), "*args"),
+            (NodeType::RestParameterNode, None, "*"),
+            (NodeType::RequiredKeywordParameterNode, Some("key"), "key:"),
+            (NodeType::KeywordRestParameterNode, None, "**"),
+            (NodeType::NoKeywordsParameterNode, None, "**nil"),
+            (NodeType::BlockParameterNode, Some("blk"), "&blk"),
+            (NodeType::ForwardingParameterNode, None, "..."),
+        ];
+
+        for (node_type, name, expected) in cases {
+            let node = parameter(node_type, name, 0, 0);
+            assert_eq!(
+                format_parameter(&node, &Source::plain("")).as_deref(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_default_values() {
+        let source = "def foo(a=1,  b:{x: 2})";
+        let optional = parameter(NodeType::OptionalParameterNode, Some("a"), 8, 11)
+            .with_children(vec![parameter(NodeType::IntegerNode, None, 10, 11)]);
+        let keyword = parameter(NodeType::OptionalKeywordParameterNode, Some("b"), 14, 22)
+            .with_children(vec![parameter(NodeType::HashNode, None, 16, 22)]);
+
+        assert_eq!(
+            format_parameter(&optional, &Source::plain(source)).as_deref(),
+            Some("a = 1")
+        );
+        assert_eq!(
+            format_parameter(&keyword, &Source::plain(source)).as_deref(),
+            Some("b: {x: 2}")
+        );
+    }
+
+    #[test]
+    fn test_multiline_default_is_not_rebuilt() {
+        let source = "def foo(a = [\n  1\n])";
+        let optional = parameter(NodeType::OptionalParameterNode, Some("a"), 8, 19)
+            .with_children(vec![parameter(NodeType::ArrayNode, None, 12, 19)]);
+
+        assert_eq!(format_parameter(&optional, &Source::plain(source)), None);
+    }
+
+    #[test]
+    fn test_destructuring_is_normalized() {
+        // def foo((a,( b, *c )))
+        let source = "def foo((a,( b, *c )))";
+        let inner = parameter(NodeType::MultiTargetNode, None, 11, 20).with_children(vec![
+            parameter(NodeType::RequiredParameterNode, Some("b"), 13, 14),
+            parameter(NodeType::SplatNode, None, 16, 18).with_children(vec![parameter(
+                NodeType::RequiredParameterNode,
+                Some("c"),
+                17,
+                18,
+            )]),
+        ]);
+        let node = parameter(NodeType::MultiTargetNode, None, 8, 21).with_children(vec![
+            parameter(NodeType::RequiredParameterNode, Some("a"), 9, 10),
+            inner,
+        ]);
+
+        assert_eq!(
+            format_parameter(&node, &Source::plain(source)).as_deref(),
+            Some("(a, (b, *c))")
+        );
+    }
+
+    #[test]
+    fn test_format_block_parameters() {
+        // |a = (x | y) ;  z|
+        let source = "|a = (x | y) ;  z|";
+        let block_params =
+            parameter(NodeType::BlockParametersNode, None, 0, 18).with_children(vec![
+                parameter(NodeType::OptionalParameterNode, Some("a"), 1, 12)
+                    .with_children(vec![parameter(NodeType::ParenthesesNode, None, 5, 12)]),
+                parameter(NodeType::BlockLocalVariableNode, Some("z"), 16, 17),
+            ]);
+
+        assert_eq!(
+            format_block_parameters(&block_params, &Source::plain(source)).as_deref(),
+            Some("|a = (x | y); z|")
+        );
+    }
+
+    #[test]
+    fn test_format_block_parameters_with_implicit_rest() {
+        let block_params =
+            parameter(NodeType::BlockParametersNode, None, 0, 5).with_children(vec![
+                parameter(NodeType::RequiredParameterNode, Some("a"), 1, 2),
+                parameter(NodeType::ImplicitRestNode, None, 3, 4),
+            ]);
+
+        assert_eq!(
+            format_block_parameters(&block_params, &Source::plain("|a ,|")).as_deref(),
+            Some("|a,|")
+        );
+    }
+
+    #[test]
+    fn test_format_empty_block_parameters() {
+        let block_params = parameter(NodeType::BlockParametersNode, None, 0, 2);
+        assert_eq!(
+            format_block_parameters(&block_params, &Source::plain("||")).as_deref(),
+            Some("||")
+        );
+    }
+}
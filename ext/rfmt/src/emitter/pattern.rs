@@ -0,0 +1,204 @@
+use super::literal::Source;
+use crate::ast::{Node, NodeType};
+use std::borrow::Cow;
+
+/// An array, find or hash pattern with its delimiters, e.g. `Point(` `x:`, `y:` `)`
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Bracketed {
+    /// Constant and opening delimiter: `[`, `{` or `Point(`
+    pub open: String,
+    pub elements: Vec<String>,
+    pub close: &'static str,
+}
+
+impl Bracketed {
+    /// Render on one line; braces get a space inside (`{ name: }`), brackets and
+    /// parentheses do not (`[a, *rest]`, `Point(x:)`)
+    pub fn render(&self) -> String {
+        let elements = self.elements.join(", ");
+        if self.open.ends_with('{') && !elements.is_empty() {
+            format!("{} {} {}", self.open, elements, self.close)
+        } else {
+            format!("{}{}{}", self.open, elements, self.close)
+        }
+    }
+}
+
+/// Render a pattern on one line with normalized spacing:
+/// `{ name: String => n }`, `[Integer, *rest]`, `Integer | Float`
+///
+/// Returns `None` when part of the pattern spans several lines.
+pub(super) fn render(node: &Node, source: &Source) -> Option<String> {
+    let text = match node.node_type {
+        NodeType::ArrayPatternNode | NodeType::HashPatternNode | NodeType::FindPatternNode => {
+            match bracketed(node, source) {
+                Some(bracketed) => bracketed.render(),
+                // Top-level patterns may omit the brackets: `in a, *rest` or `in name:`
+                None => elements(node, source)?.join(", "),
+            }
+        }
+        NodeType::AlternationPatternNode => match node.children.as_slice() {
+            [left, right] => format!("{} | {}", render(left, source)?, render(right, source)?),
+            _ => return None,
+        },
+        NodeType::CapturePatternNode => match node.children.as_slice() {
+            [value, target] => format!("{} => {}", render(value, source)?, text(target, source)?),
+            _ => return None,
+        },
+        NodeType::SplatNode => match node.children.first() {
+            Some(target) => format!("*{}", text(target, source)?),
+            None => "*".to_string(),
+        },
+        NodeType::AssocSplatNode => match node.children.first() {
+            Some(target) => format!("**{}", text(target, source)?),
+            None => "**".to_string(),
+        },
+        NodeType::NoKeywordsParameterNode => "**nil".to_string(),
+        // `name:` shorthand or `name: pattern`
+        NodeType::AssocNode => {
+            let key = text(node.children.first()?, source)?;
+            match node.children.get(1) {
+                Some(value) if !matches!(value.node_type, NodeType::ImplicitNode) => {
+                    format!("{} {}", key, render(value, source)?)
+                }
+                _ => key.to_string(),
+            }
+        }
+        // Values, ranges, variables and pinned expressions are kept as written
+        _ => text(node, source)?.to_string(),
+    };
+
+    Some(text)
+}
+
+/// The delimiters and elements of an array, find or hash pattern, if it has brackets
+pub(super) fn bracketed(node: &Node, source: &Source) -> Option<Bracketed> {
+    let opening = node.metadata.get("opening")?;
+    let close = match opening.as_str() {
+        "[" => "]",
+        "(" => ")",
+        "{" => "}",
+        _ => return None,
+    };
+    let constant = node.metadata.get("constant").map_or("", String::as_str);
+
+    Some(Bracketed {
+        open: format!("{}{}", constant, opening),
+        elements: elements(node, source)?,
+        close,
+    })
+}
+
+/// Elements of an array, find or hash pattern; an implicit rest (`[a,]`) is kept as a
+/// trailing comma on the last element
+fn elements(node: &Node, source: &Source) -> Option<Vec<String>> {
+    let mut elements: Vec<String> = Vec::with_capacity(node.children.len());
+    for child in &node.children {
+        if matches!(child.node_type, NodeType::ImplicitRestNode) {
+            elements.last_mut()?.push(',');
+        } else {
+            elements.push(render(child, source)?);
+        }
+    }
+    Some(elements)
+}
+
+/// Source text of a node, if it fits on one line
+fn text<'a>(node: &Node, source: &Source<'a>) -> Option<Cow<'a, str>> {
+    source.line(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn node(node_type: NodeType, start: usize, end: usize) -> Node {
+        Node::new(node_type, Location::new(1, start, 1, end, start, end))
+    }
+
+    /// A `local_variable_target_node`, which the AST does not model
+    fn target(start: usize, end: usize) -> Node {
+        node(
+            NodeType::Unknown("local_variable_target_node".to_string()),
+            start,
+            end,
+        )
+    }
+
+    fn with_metadata(mut node: Node, entries: &[(&str, &str)]) -> Node {
+        for (key, value) in entries {
+            node.metadata.insert(key.to_string(), value.to_string());
+        }
+        node
+    }
+
+    #[test]
+    fn test_render_hash_pattern() {
+        // {name:String=>n,  age:}
+        let source = &Source::plain("{name:String=>n,  age:}");
+        let capture = node(NodeType::CapturePatternNode, 6, 15).with_children(vec![
+            node(NodeType::ConstantReadNode, 6, 12),
+            target(14, 15),
+        ]);
+        let pattern = with_metadata(node(NodeType::HashPatternNode, 0, 23), &[("opening", "{")])
+            .with_children(vec![
+                node(NodeType::AssocNode, 1, 15)
+                    .with_children(vec![node(NodeType::SymbolNode, 1, 6), capture]),
+                node(NodeType::AssocNode, 18, 22).with_children(vec![node(
+                    NodeType::SymbolNode,
+                    18,
+                    22,
+                )]),
+            ]);
+
+        assert_eq!(
+            render(&pattern, source).as_deref(),
+            Some("{ name: String => n, age: }")
+        );
+    }
+
+    #[test]
+    fn test_render_array_pattern_with_constant() {
+        // Point[ Integer|Float ,*rest ]
+        let source = &Source::plain("Point[ Integer|Float ,*rest ]");
+        let alternation = node(NodeType::AlternationPatternNode, 7, 20).with_children(vec![
+            node(NodeType::ConstantReadNode, 7, 14),
+            node(NodeType::ConstantReadNode, 15, 20),
+        ]);
+        let rest = node(NodeType::SplatNode, 22, 27).with_children(vec![target(23, 27)]);
+        let pattern = with_metadata(
+            node(NodeType::ArrayPatternNode, 0, 29),
+            &[("constant", "Point"), ("opening", "[")],
+        )
+        .with_children(vec![alternation, rest]);
+
+        assert_eq!(
+            render(&pattern, source).as_deref(),
+            Some("Point[Integer | Float, *rest]")
+        );
+    }
+
+    #[test]
+    fn test_render_bracketless_and_implicit_rest() {
+        // a , b,
+        let source = &Source::plain("a , b,");
+        let pattern = node(NodeType::ArrayPatternNode, 0, 6).with_children(vec![
+            target(0, 1),
+            target(4, 5),
+            node(NodeType::ImplicitRestNode, 5, 6),
+        ]);
+
+        assert_eq!(render(&pattern, source).as_deref(), Some("a, b,"));
+        assert_eq!(bracketed(&pattern, source), None);
+    }
+
+    #[test]
+    fn test_render_empty_hash_pattern() {
+        let pattern = with_metadata(node(NodeType::HashPatternNode, 0, 2), &[("opening", "{")]);
+        assert_eq!(
+            render(&pattern, &Source::plain("{}")).as_deref(),
+            Some("{}")
+        );
+    }
+}
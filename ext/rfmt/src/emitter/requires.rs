@@ -0,0 +1,182 @@
+use crate::ast::{Node, NodeType};
+
+/// Libraries shipped with Ruby, matched against the first segment of a required path
+/// (`net/http` is matched by `net`)
+const STDLIB: &[&str] = &[
+    "English",
+    "abbrev",
+    "base64",
+    "benchmark",
+    "bigdecimal",
+    "cgi",
+    "coverage",
+    "csv",
+    "date",
+    "delegate",
+    "did_you_mean",
+    "digest",
+    "drb",
+    "erb",
+    "etc",
+    "expect",
+    "fcntl",
+    "fiber",
+    "fiddle",
+    "fileutils",
+    "find",
+    "forwardable",
+    "getoptlong",
+    "io",
+    "ipaddr",
+    "irb",
+    "json",
+    "logger",
+    "matrix",
+    "monitor",
+    "mutex_m",
+    "net",
+    "nkf",
+    "objspace",
+    "observer",
+    "open-uri",
+    "open3",
+    "openssl",
+    "optparse",
+    "ostruct",
+    "pathname",
+    "pp",
+    "prettyprint",
+    "prime",
+    "prism",
+    "pstore",
+    "psych",
+    "racc",
+    "rbconfig",
+    "rdoc",
+    "readline",
+    "reline",
+    "resolv",
+    "ripper",
+    "rubygems",
+    "securerandom",
+    "set",
+    "shellwords",
+    "singleton",
+    "socket",
+    "stringio",
+    "strscan",
+    "syslog",
+    "tempfile",
+    "time",
+    "timeout",
+    "tmpdir",
+    "tsort",
+    "un",
+    "uri",
+    "weakref",
+    "yaml",
+    "zlib",
+];
+
+/// Where a required file comes from; runs of `require` lines are sorted in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Group {
+    Stdlib,
+    Gem,
+    Relative,
+}
+
+/// The group and path of a `require 'path'`, `require_relative 'path'` or
+/// `autoload :Name, 'path'` call with a plain string path
+pub(super) fn required(node: &Node) -> Option<(Group, &str)> {
+    if !matches!(node.node_type, NodeType::CallNode) || node.metadata.contains_key("call_operator")
+    {
+        return None;
+    }
+
+    let name = node.metadata.get("name")?.as_str();
+    let path = match (name, node.children.as_slice()) {
+        ("require" | "require_relative", [path]) => path,
+        ("autoload", [constant, path]) if matches!(constant.node_type, NodeType::SymbolNode) => {
+            path
+        }
+        _ => return None,
+    };
+    if !matches!(path.node_type, NodeType::StringNode) {
+        return None;
+    }
+    let path = path.metadata.get("content")?.as_str();
+
+    let first_segment = path.split('/').next().unwrap_or(path);
+    let group = if name == "require_relative" || path.starts_with("./") || path.starts_with("../") {
+        Group::Relative
+    } else if STDLIB.binary_search(&first_segment).is_ok() {
+        Group::Stdlib
+    } else {
+        Group::Gem
+    };
+    Some((group, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Location;
+
+    fn node(node_type: NodeType) -> Node {
+        Node::new(node_type, Location::new(1, 0, 1, 10, 0, 10))
+    }
+
+    fn call(name: &str, arguments: Vec<Node>) -> Node {
+        let mut call = node(NodeType::CallNode);
+        call.metadata.insert("name".to_string(), name.to_string());
+        call.with_children(arguments)
+    }
+
+    fn string(content: &str) -> Node {
+        let mut string = node(NodeType::StringNode);
+        string
+            .metadata
+            .insert("content".to_string(), content.to_string());
+        string
+    }
+
+    #[test]
+    fn test_stdlib_is_sorted() {
+        assert!(STDLIB.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(
+            required(&call("require", vec![string("net/http")])),
+            Some((Group::Stdlib, "net/http"))
+        );
+        assert_eq!(
+            required(&call("require", vec![string("rails")])),
+            Some((Group::Gem, "rails"))
+        );
+        assert_eq!(
+            required(&call("require", vec![string("./lib/app")])),
+            Some((Group::Relative, "./lib/app"))
+        );
+        assert_eq!(
+            required(&call("require_relative", vec![string("support")])),
+            Some((Group::Relative, "support"))
+        );
+        assert_eq!(
+            required(&call(
+                "autoload",
+                vec![node(NodeType::SymbolNode), string("app/parser")]
+            )),
+            Some((Group::Gem, "app/parser"))
+        );
+    }
+
+    #[test]
+    fn test_dynamic_paths_are_not_sorted() {
+        let dynamic = call("require", vec![node(NodeType::InterpolatedStringNode)]);
+        assert_eq!(required(&dynamic), None);
+        assert_eq!(required(&call("puts", vec![string("json")])), None);
+    }
+}
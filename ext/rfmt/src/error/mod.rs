@@ -1,4 +1,4 @@
-use magnus::{Error as MagnusError, Ruby};
+use magnus::{prelude::*, Error as MagnusError, ExceptionClass, Ruby};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, RfmtError>;
@@ -19,6 +19,9 @@ pub enum RfmtError {
 
     #[error("Configuration error: {message}")]
     ConfigError { message: String },
+
+    #[error("Timeout: {message}")]
+    Timeout { message: String },
 }
 
 // Implement From for std::fmt::Error
@@ -36,11 +39,19 @@ impl RfmtError {
             RfmtError::FormatError(_) => "FormatError",
             RfmtError::UnsupportedFeature { .. } => "UnsupportedFeature",
             RfmtError::ConfigError { .. } => "ConfigError",
+            RfmtError::Timeout { .. } => "Timeout",
+        };
+
+        // Timeouts get their own class so that callers such as editors can tell a
+        // request that ran out of time from a formatting failure
+        let class = match self {
+            RfmtError::Timeout { .. } => ruby
+                .define_module("Rfmt")
+                .and_then(|module| module.const_get::<_, ExceptionClass>("TimeoutError"))
+                .unwrap_or_else(|_| ruby.exception_standard_error()),
+            _ => ruby.exception_standard_error(),
         };
 
-        MagnusError::new(
-            ruby.exception_standard_error(),
-            format!("[Rfmt::{}] {}", exception_class, self),
-        )
+        MagnusError::new(class, format!("[Rfmt::{}] {}", exception_class, self))
     }
 }
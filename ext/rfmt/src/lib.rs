@@ -3,54 +3,138 @@ mod config;
 mod emitter;
 mod error;
 mod logging;
+mod panic_guard;
 mod parser;
 mod policy;
+mod source;
 
 use policy::SecurityPolicy;
 
 use config::Config;
-use emitter::Emitter;
-use magnus::{function, prelude::*, Error, Ruby};
-use parser::{PrismAdapter, RubyParser};
+use emitter::{Emitter, FileKind};
+use magnus::{function, prelude::*, Error, RString, Ruby};
+use panic_guard::catch_panic;
+use parser::{validate_locations, PrismAdapter, RubyParser};
+use source::SourceText;
 
-fn format_ruby_code(ruby: &Ruby, source: String, json: String) -> Result<String, Error> {
-    let policy = SecurityPolicy::default();
+/// A diagnostic as `[message, start_line, start_column, end_line, end_column]`,
+/// with 1-based lines and 0-based columns
+type DiagnosticTuple = (String, usize, usize, usize, usize);
 
-    policy
-        .validate_source_size(&source)
-        .map_err(|e| e.to_magnus_error(ruby))?;
+fn format_ruby_code(
+    ruby: &Ruby,
+    source: RString,
+    json: String,
+    path: String,
+    timeout: Option<f64>,
+) -> Result<RString, Error> {
+    format_source(ruby, source, json, path, timeout).map(|(formatted, _)| formatted)
+}
+
+/// Like `format_ruby_code`, also returning the problems found in the source, such
+/// as gems declared twice in a Gemfile
+fn format_ruby_code_with_diagnostics(
+    ruby: &Ruby,
+    source: RString,
+    json: String,
+    path: String,
+    timeout: Option<f64>,
+) -> Result<(RString, Vec<DiagnosticTuple>), Error> {
+    format_source(ruby, source, json, path, timeout)
+}
+
+fn format_source(
+    ruby: &Ruby,
+    source: RString,
+    json: String,
+    path: String,
+    timeout: Option<f64>,
+) -> Result<(RString, Vec<DiagnosticTuple>), Error> {
+    catch_panic(ruby, || {
+        let mut policy = SecurityPolicy::default();
+        if let Some(seconds) = timeout {
+            policy = policy
+                .with_timeout_secs(seconds)
+                .map_err(|e| e.to_magnus_error(ruby))?;
+        }
+
+        // Copy the raw bytes out before calling back into Ruby; they may not be UTF-8
+        let bytes = unsafe { source.as_slice() }.to_vec();
+
+        policy
+            .validate_source_size(&bytes)
+            .map_err(|e| e.to_magnus_error(ruby))?;
+
+        let deadline = policy.deadline();
+
+        let config = Config::discover().map_err(|e| e.to_magnus_error(ruby))?;
+        let source_text = SourceText::decode(&bytes, &config.parser.encoding)
+            .map_err(|e| e.to_magnus_error(ruby))?;
+
+        let parser = PrismAdapter::new().with_deadline(deadline);
+        let mut ast = parser.parse(&json).map_err(|e| e.to_magnus_error(ruby))?;
+        source_text.remap_locations(&mut ast);
+        validate_locations(&ast, source_text.text()).map_err(|e| e.to_magnus_error(ruby))?;
 
-    let parser = PrismAdapter::new();
-    let ast = parser.parse(&json).map_err(|e| e.to_magnus_error(ruby))?;
+        let end_of_line = config.formatting.end_of_line.clone();
+        let mut emitter = Emitter::with_source(config, source_text.text().to_string())
+            .with_deadline(deadline)
+            .with_file_kind(FileKind::from_path(&path));
 
-    let config = Config::discover().map_err(|e| e.to_magnus_error(ruby))?;
-    let mut emitter = Emitter::with_source(config, source);
+        let formatted = emitter.emit(&ast).map_err(|e| e.to_magnus_error(ruby))?;
+        let diagnostics = emitter
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                let location = &diagnostic.location;
+                (
+                    diagnostic.message.clone(),
+                    location.start_line,
+                    location.start_column,
+                    location.end_line,
+                    location.end_column,
+                )
+            })
+            .collect();
+        let output = source_text
+            .encode(&formatted, &end_of_line)
+            .map_err(|e| e.to_magnus_error(ruby))?;
 
-    let formatted = emitter.emit(&ast).map_err(|e| e.to_magnus_error(ruby))?;
+        // Return the bytes tagged with the caller's encoding, like the input
+        let result = ruby.str_from_slice(&output);
+        result.enc_associate(source.enc_get())?;
 
-    Ok(formatted)
+        Ok((result, diagnostics))
+    })
 }
 
 /// Parse Ruby source code and return JSON AST representation
 /// This is useful for debugging and integration testing
 fn parse_to_json(ruby: &Ruby, source: String) -> Result<String, Error> {
-    let parser = PrismAdapter::new();
-    let ast = parser.parse(&source).map_err(|e| e.to_magnus_error(ruby))?;
+    catch_panic(ruby, || {
+        let parser = PrismAdapter::new().with_deadline(SecurityPolicy::default().deadline());
+        let ast = parser.parse(&source).map_err(|e| e.to_magnus_error(ruby))?;
 
-    Ok(format!("{:#?}", ast))
+        Ok(format!("{:#?}", ast))
+    })
 }
 
-fn rust_version() -> String {
-    "0.2.0 (Rust)".to_string()
+fn rust_version(ruby: &Ruby) -> Result<String, Error> {
+    catch_panic(ruby, || Ok("0.2.0 (Rust)".to_string()))
 }
 
 #[magnus::init]
 fn init(ruby: &Ruby) -> Result<(), Error> {
     logging::RfmtLogger::init();
+    panic_guard::install_hook();
 
     let module = ruby.define_module("Rfmt")?;
 
-    module.define_singleton_method("format_code", function!(format_ruby_code, 2))?;
+    module.define_singleton_method("format_code", function!(format_ruby_code, 4))?;
+    module.define_singleton_method(
+        "format_code_with_diagnostics",
+        function!(format_ruby_code_with_diagnostics, 4),
+    )?;
     module.define_singleton_method("parse_to_json", function!(parse_to_json, 1))?;
     module.define_singleton_method("rust_version", function!(rust_version, 0))?;
 
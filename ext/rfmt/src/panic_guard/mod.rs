@@ -0,0 +1,150 @@
+use magnus::{prelude::*, Error, ExceptionClass, Ruby};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+
+thread_local! {
+    /// Whether the current thread is running inside `catch_panic`
+    static IN_GUARD: Cell<bool> = const { Cell::new(false) };
+
+    /// Panic location and backtrace recorded by the hook for the panic being caught
+    static LAST_PANIC: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+struct PanicReport {
+    location: Option<String>,
+    backtrace: Option<String>,
+}
+
+/// Install a panic hook that records details of panics raised inside `catch_panic`
+///
+/// Panics outside the guard (e.g. from other native extensions in the same process)
+/// are forwarded to the previous hook unchanged.
+pub fn install_hook() {
+    let previous_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        if !IN_GUARD.with(Cell::get) {
+            previous_hook(info);
+            return;
+        }
+
+        let backtrace =
+            debug_enabled().then(|| std::backtrace::Backtrace::force_capture().to_string());
+        let report = PanicReport {
+            location: info.location().map(|l| l.to_string()),
+            backtrace,
+        };
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(report));
+    }));
+}
+
+/// Run `f`, converting any Rust panic into an `Rfmt::InternalError` Ruby exception
+///
+/// Unwinding across the FFI boundary would abort the host Ruby process
+/// (ruby-lsp, Puma, ...), so every exported native function goes through here.
+pub fn catch_panic<T, F>(ruby: &Ruby, f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    let was_guarded = IN_GUARD.with(|guard| guard.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_GUARD.with(|guard| guard.set(was_guarded));
+
+    result.unwrap_or_else(|payload| {
+        let report = LAST_PANIC.with(|last| last.borrow_mut().take());
+        Err(Error::new(
+            internal_error_class(ruby),
+            internal_error_message(payload.as_ref(), report.as_ref()),
+        ))
+    })
+}
+
+/// Look up `Rfmt::InternalError`, falling back to StandardError if the Ruby side
+/// has not defined it (e.g. when the extension is loaded on its own)
+fn internal_error_class(ruby: &Ruby) -> ExceptionClass {
+    ruby.define_module("Rfmt")
+        .and_then(|module| module.const_get::<_, ExceptionClass>("InternalError"))
+        .unwrap_or_else(|_| ruby.exception_standard_error())
+}
+
+fn internal_error_message(payload: &(dyn Any + Send), report: Option<&PanicReport>) -> String {
+    let mut message = format!(
+        "[Rfmt::InternalError] Internal error: {}",
+        panic_message(payload)
+    );
+
+    if let Some(location) = report.and_then(|r| r.location.as_deref()) {
+        message.push_str(&format!(" (at {})", location));
+    }
+
+    if let Some(backtrace) = report.and_then(|r| r.backtrace.as_deref()) {
+        message.push_str("\n\nBacktrace:\n");
+        message.push_str(backtrace);
+    }
+
+    message.push_str("\n\nPlease report this as a bug at: https://github.com/fs0414/rfmt/issues");
+    message
+}
+
+/// Extract the message passed to `panic!`
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn debug_enabled() -> bool {
+    std::env::var("RFMT_DEBUG").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message_from_str() {
+        let payload: Box<dyn Any + Send> = Box::new("byte index 5 is out of bounds");
+        assert_eq!(
+            panic_message(payload.as_ref()),
+            "byte index 5 is out of bounds"
+        );
+    }
+
+    #[test]
+    fn test_panic_message_from_string() {
+        let payload: Box<dyn Any + Send> = Box::new(format!("bad offset {}", 42));
+        assert_eq!(panic_message(payload.as_ref()), "bad offset 42");
+    }
+
+    #[test]
+    fn test_panic_message_unknown_payload() {
+        let payload: Box<dyn Any + Send> = Box::new(42_u32);
+        assert_eq!(panic_message(payload.as_ref()), "unknown panic");
+    }
+
+    #[test]
+    fn test_internal_error_message_with_report() {
+        let payload: Box<dyn Any + Send> = Box::new("boom");
+        let report = PanicReport {
+            location: Some("src/emitter/mod.rs:10:5".to_string()),
+            backtrace: Some("0: rfmt::emitter".to_string()),
+        };
+
+        let message = internal_error_message(payload.as_ref(), Some(&report));
+        assert!(message.starts_with("[Rfmt::InternalError] Internal error: boom"));
+        assert!(message.contains("(at src/emitter/mod.rs:10:5)"));
+        assert!(message.contains("Backtrace:\n0: rfmt::emitter"));
+    }
+
+    #[test]
+    fn test_internal_error_message_without_report() {
+        let payload: Box<dyn Any + Send> = Box::new("boom");
+        let message = internal_error_message(payload.as_ref(), None);
+        assert!(!message.contains("Backtrace"));
+    }
+}
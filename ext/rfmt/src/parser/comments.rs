@@ -0,0 +1,185 @@
+use crate::ast::{Comment, CommentPosition, Node, NodeType};
+
+/// Attach each comment to the node it belongs to, with its `CommentPosition`
+///
+/// Inside the deepest node that encloses the comment:
+/// - a comment on the line where the preceding child ends trails that child
+/// - a comment on the first line of the enclosing node (`def foo # note`), or
+///   with no following child, is an inner comment of the enclosing node
+/// - otherwise it leads the following child
+///
+/// Statement lists are looked through, so a comment above a method leads the
+/// `def` rather than the body that contains it.
+pub fn attach_comments(root: &mut Node, comments: Vec<Comment>) {
+    for mut comment in comments {
+        let (path, position) = placement(root, &comment);
+        comment.position = position;
+
+        let mut node = &mut *root;
+        for i in path {
+            node = &mut node.children[i];
+        }
+        node.comments.push(comment);
+    }
+}
+
+/// Child indices leading from `root` to the node that owns `comment`, and the
+/// comment's position relative to that node
+fn placement(root: &Node, comment: &Comment) -> (Vec<usize>, CommentPosition) {
+    let (start, end) = (comment.location.start_offset, comment.location.end_offset);
+    let mut path = Vec::new();
+    let mut node = root;
+    while let Some(i) = node
+        .children
+        .iter()
+        .position(|child| child.location.start_offset <= start && end <= child.location.end_offset)
+    {
+        path.push(i);
+        node = &node.children[i];
+    }
+
+    let line = comment.location.start_line;
+    let preceding = node
+        .children
+        .iter()
+        .rposition(|child| child.location.end_offset <= start);
+    if let Some(i) = preceding {
+        if node.children[i].location.end_line == line {
+            path.push(i);
+            descend(root, &mut path, |statements| statements.children.len() - 1);
+            return (path, CommentPosition::Trailing);
+        }
+    }
+
+    if !path.is_empty() && node.location.start_line == line {
+        return (path, CommentPosition::Inner);
+    }
+
+    let following = node
+        .children
+        .iter()
+        .position(|child| child.location.start_offset >= end);
+    match following {
+        Some(i) => {
+            path.push(i);
+            descend(root, &mut path, |_| 0);
+            (path, CommentPosition::Leading)
+        }
+        None => (path, CommentPosition::Inner),
+    }
+}
+
+/// Extend `path` through statement lists to the statement picked by `pick`
+fn descend(root: &Node, path: &mut Vec<usize>, pick: impl Fn(&Node) -> usize) {
+    let mut node = path.iter().fold(root, |node, &i| &node.children[i]);
+    while matches!(node.node_type, NodeType::StatementsNode) && !node.children.is_empty() {
+        let i = pick(node);
+        path.push(i);
+        node = &node.children[i];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{CommentType, Location};
+
+    /// A node or comment location on one line, with offsets `line * 100 + column`
+    fn at(line: usize, start: usize, end: usize) -> Location {
+        Location::new(line, start, line, end, line * 100 + start, line * 100 + end)
+    }
+
+    fn spanning(first: &Location, last: &Location) -> Location {
+        Location::new(
+            first.start_line,
+            first.start_column,
+            last.end_line,
+            last.end_column,
+            first.start_offset,
+            last.end_offset,
+        )
+    }
+
+    fn comment(location: Location) -> Comment {
+        Comment {
+            text: "# note".to_string(),
+            location,
+            comment_type: CommentType::Line,
+            position: CommentPosition::Leading,
+        }
+    }
+
+    /// class Foo       # 1: trailing `Foo`
+    ///   # 2: doc
+    ///   def bar       # 3: inner, `def` has no child before it
+    ///   end           # 4: trailing `def`
+    ///   # 5: before `end`
+    /// end
+    fn class() -> Node {
+        let def = Node::new(NodeType::DefNode, spanning(&at(3, 2, 9), &at(4, 2, 5)));
+        let body =
+            Node::new(NodeType::StatementsNode, def.location.clone()).with_children(vec![def]);
+        let name = Node::new(NodeType::ConstantReadNode, at(1, 6, 9));
+        let class = Node::new(NodeType::ClassNode, spanning(&at(1, 0, 9), &at(6, 0, 3)))
+            .with_children(vec![name, body]);
+        Node::new(NodeType::ProgramNode, class.location.clone()).with_children(vec![class])
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut root = class();
+        attach_comments(
+            &mut root,
+            vec![
+                comment(at(1, 10, 16)),
+                comment(at(2, 2, 8)),
+                comment(at(3, 10, 16)),
+                comment(at(4, 6, 12)),
+                comment(at(5, 2, 8)),
+                comment(at(7, 0, 6)),
+            ],
+        );
+
+        let class = &root.children[0];
+        let def = &class.children[1].children[0];
+        let positions = |node: &Node| -> Vec<(usize, CommentPosition)> {
+            node.comments
+                .iter()
+                .map(|c| (c.location.start_line, c.position.clone()))
+                .collect()
+        };
+
+        assert_eq!(
+            positions(&class.children[0]),
+            [(1, CommentPosition::Trailing)]
+        );
+        assert_eq!(positions(class), [(5, CommentPosition::Inner)]);
+        assert_eq!(
+            positions(def),
+            [
+                (2, CommentPosition::Leading),
+                (3, CommentPosition::Inner),
+                (4, CommentPosition::Trailing)
+            ]
+        );
+        assert_eq!(positions(&root), [(7, CommentPosition::Inner)]);
+    }
+
+    #[test]
+    fn test_trailing_argument_comment() {
+        // foo(1, # note
+        //     2)
+        let one = Node::new(NodeType::IntegerNode, at(1, 4, 5));
+        let two = Node::new(NodeType::IntegerNode, at(2, 4, 5));
+        let call = Node::new(NodeType::CallNode, spanning(&at(1, 0, 3), &at(2, 0, 6)))
+            .with_children(vec![one, two]);
+        let mut root =
+            Node::new(NodeType::ProgramNode, call.location.clone()).with_children(vec![call]);
+
+        attach_comments(&mut root, vec![comment(at(1, 7, 13))]);
+
+        let one = &root.children[0].children[0];
+        assert_eq!(one.comments.len(), 1);
+        assert_eq!(one.comments[0].position, CommentPosition::Trailing);
+    }
+}
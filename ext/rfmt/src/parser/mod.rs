@@ -1,8 +1,11 @@
 use crate::ast::Node;
 use crate::error::Result;
 
+pub mod comments;
 pub mod prism_adapter;
+pub mod validation;
 pub use prism_adapter::PrismAdapter;
+pub use validation::validate_locations;
 
 pub trait RubyParser: Send + Sync {
     fn parse(&self, source: &str) -> Result<Node>;
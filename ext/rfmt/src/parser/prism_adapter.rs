@@ -1,16 +1,28 @@
 use crate::ast::{Comment, CommentPosition, CommentType, FormattingInfo, Location, Node, NodeType};
 use crate::error::{Result, RfmtError};
+use crate::parser::comments::attach_comments;
 use crate::parser::RubyParser;
+use crate::policy::Deadline;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Prism parser adapter
 /// This integrates with Ruby Prism parser via Magnus FFI
-pub struct PrismAdapter;
+pub struct PrismAdapter {
+    deadline: Deadline,
+}
 
 impl PrismAdapter {
     pub fn new() -> Self {
-        Self
+        Self {
+            deadline: Deadline::unlimited(),
+        }
+    }
+
+    /// Abort conversion with `RfmtError::Timeout` once the deadline passes
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
     }
 
     /// Parse JSON from Ruby's `PrismBridge`
@@ -27,7 +39,9 @@ impl PrismAdapter {
     }
 
     /// Convert `PrismNode` to internal `Node` representation
-    fn convert_node(prism_node: &PrismNode) -> Result<Node> {
+    fn convert_node(&self, prism_node: &PrismNode) -> Result<Node> {
+        self.deadline.check()?;
+
         // Convert node type (always succeeds, returns Unknown for unsupported types)
         let node_type = NodeType::from_str(&prism_node.node_type);
 
@@ -42,8 +56,11 @@ impl PrismAdapter {
         );
 
         // Convert children recursively
-        let children: Result<Vec<Node>> =
-            prism_node.children.iter().map(Self::convert_node).collect();
+        let children: Result<Vec<Node>> = prism_node
+            .children
+            .iter()
+            .map(|child| self.convert_node(child))
+            .collect();
         let children = children?;
 
         // Convert comments
@@ -94,13 +111,16 @@ impl PrismAdapter {
 impl RubyParser for PrismAdapter {
     fn parse(&self, json: &str) -> Result<Node> {
         let (prism_ast, top_level_comments) = Self::parse_json(json)?;
-        let mut node = Self::convert_node(&prism_ast)?;
-
-        // Attach top-level comments to the root node
-        if !top_level_comments.is_empty() {
-            node.comments
-                .extend(top_level_comments.iter().map(Self::convert_comment));
-        }
+        let mut node = self.convert_node(&prism_ast)?;
+
+        // Attach top-level comments to the nodes they belong to
+        attach_comments(
+            &mut node,
+            top_level_comments
+                .iter()
+                .map(Self::convert_comment)
+                .collect(),
+        );
 
         Ok(node)
     }
@@ -368,7 +388,7 @@ mod tests {
 
         let node = result.unwrap();
         assert_eq!(node.node_type, NodeType::ClassNode);
-        assert_eq!(node.formatting.multiline, true);
+        assert!(node.formatting.multiline);
         assert!(node.is_multiline());
         assert_eq!(node.line_count(), 3);
     }
@@ -389,6 +409,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_with_expired_deadline() {
+        let adapter = PrismAdapter::new().with_deadline(Deadline::after(std::time::Duration::ZERO));
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        let json = r#"{
+            "node_type": "program_node",
+            "location": {
+                "start_line": 1,
+                "start_column": 0,
+                "end_line": 1,
+                "end_column": 0,
+                "start_offset": 0,
+                "end_offset": 0
+            },
+            "children": [],
+            "metadata": {},
+            "comments": [],
+            "formatting": {
+                "indent_level": 0,
+                "needs_blank_line_before": false,
+                "needs_blank_line_after": false,
+                "preserve_newlines": false,
+                "multiline": false,
+                "original_formatting": null
+            }
+        }"#;
+
+        assert!(matches!(
+            adapter.parse(json),
+            Err(RfmtError::Timeout { .. })
+        ));
+    }
+
     #[test]
     fn test_parse_unknown_node_type() {
         let adapter = PrismAdapter::new();
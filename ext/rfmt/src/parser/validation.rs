@@ -0,0 +1,224 @@
+use crate::ast::{Location, Node};
+use crate::error::{Result, RfmtError};
+
+/// Validate every node and comment `Location` against the source it was parsed from
+///
+/// The emitter slices `source` by these offsets, so a bad location would otherwise
+/// drop code from the output silently. Checks that:
+/// - offsets are in bounds and `start_offset <= end_offset`
+/// - offsets fall on UTF-8 character boundaries
+/// - child nodes are nested within their parent
+/// - line/column numbers agree with the offsets
+pub fn validate_locations(ast: &Node, source: &str) -> Result<()> {
+    let lines = LineIndex::new(source);
+    let mut stack: Vec<(&Node, Option<&Node>)> = vec![(ast, None)];
+
+    while let Some((node, parent)) = stack.pop() {
+        let describe = || format!("{:?}", node.node_type);
+
+        validate_location(&node.location, source, &lines, describe)?;
+
+        if let Some(parent) = parent {
+            if node.location.start_offset < parent.location.start_offset
+                || node.location.end_offset > parent.location.end_offset
+            {
+                return Err(RfmtError::PrismError(format!(
+                    "{:?} at {}..{} is not nested within parent {:?} at {}..{}",
+                    node.node_type,
+                    node.location.start_offset,
+                    node.location.end_offset,
+                    parent.node_type,
+                    parent.location.start_offset,
+                    parent.location.end_offset
+                )));
+            }
+        }
+
+        for comment in &node.comments {
+            validate_location(&comment.location, source, &lines, || "comment".to_string())?;
+        }
+
+        stack.extend(node.children.iter().map(|child| (child, Some(node))));
+    }
+
+    Ok(())
+}
+
+fn validate_location(
+    location: &Location,
+    source: &str,
+    lines: &LineIndex,
+    describe: impl Fn() -> String,
+) -> Result<()> {
+    let invalid = |reason: String| {
+        Err(RfmtError::PrismError(format!(
+            "Invalid location for {} ({}:{}-{}:{}, offsets {}..{}): {}",
+            describe(),
+            location.start_line,
+            location.start_column,
+            location.end_line,
+            location.end_column,
+            location.start_offset,
+            location.end_offset,
+            reason
+        )))
+    };
+
+    if location.start_offset > location.end_offset {
+        return invalid("start offset is after end offset".to_string());
+    }
+
+    if location.end_offset > source.len() {
+        return invalid(format!("offset exceeds source length {}", source.len()));
+    }
+
+    for offset in [location.start_offset, location.end_offset] {
+        if !source.is_char_boundary(offset) {
+            return invalid(format!(
+                "offset {} is not on a UTF-8 character boundary",
+                offset
+            ));
+        }
+    }
+
+    let (start_line, start_column) = lines.position(location.start_offset);
+    if (start_line, start_column) != (location.start_line, location.start_column) {
+        return invalid(format!(
+            "start offset is at {}:{} in the source",
+            start_line, start_column
+        ));
+    }
+
+    let (end_line, end_column) = lines.position(location.end_offset);
+    if (end_line, end_column) != (location.end_line, location.end_column) {
+        return invalid(format!(
+            "end offset is at {}:{} in the source",
+            end_line, end_column
+        ));
+    }
+
+    Ok(())
+}
+
+/// Byte offsets of line starts, for mapping offsets to 1-based lines and byte columns
+struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    /// Line (1-based) and byte column (0-based) of an offset, using Prism's conventions
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        (line, offset - self.line_starts[line - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::NodeType;
+
+    fn node_at(node_type: NodeType, source: &str, start: usize, end: usize) -> Node {
+        let lines = LineIndex::new(source);
+        let (start_line, start_column) = lines.position(start);
+        let (end_line, end_column) = lines.position(end);
+        Node::new(
+            node_type,
+            Location::new(start_line, start_column, end_line, end_column, start, end),
+        )
+    }
+
+    #[test]
+    fn test_valid_locations() {
+        let source = "foo\nbar(1)\n";
+        let call = node_at(NodeType::CallNode, source, 4, 10).with_children(vec![node_at(
+            NodeType::IntegerNode,
+            source,
+            8,
+            9,
+        )]);
+        let program = node_at(NodeType::ProgramNode, source, 0, 10).with_children(vec![call]);
+
+        assert!(validate_locations(&program, source).is_ok());
+    }
+
+    #[test]
+    fn test_offset_out_of_bounds() {
+        let source = "foo";
+        let node = Node::new(NodeType::CallNode, Location::new(1, 0, 1, 10, 0, 10));
+
+        let err = validate_locations(&node, source).unwrap_err();
+        assert!(matches!(err, RfmtError::PrismError(ref msg) if msg.contains("source length")));
+    }
+
+    #[test]
+    fn test_offset_inside_multibyte_character() {
+        // "あ" is 3 bytes, offset 1 falls inside it
+        let source = "\"あ\"";
+        let node = Node::new(NodeType::StringNode, Location::new(1, 0, 1, 2, 0, 2));
+
+        let err = validate_locations(&node, source).unwrap_err();
+        assert!(
+            matches!(err, RfmtError::PrismError(ref msg) if msg.contains("character boundary"))
+        );
+    }
+
+    #[test]
+    fn test_child_outside_parent() {
+        let source = "foo(1) + 2";
+        let call = node_at(NodeType::CallNode, source, 0, 6).with_children(vec![node_at(
+            NodeType::IntegerNode,
+            source,
+            9,
+            10,
+        )]);
+
+        let err = validate_locations(&call, source).unwrap_err();
+        assert!(matches!(err, RfmtError::PrismError(ref msg) if msg.contains("not nested")));
+    }
+
+    #[test]
+    fn test_line_mismatch() {
+        let source = "foo\nbar\n";
+        let node = Node::new(NodeType::CallNode, Location::new(1, 0, 1, 3, 4, 7));
+
+        let err = validate_locations(&node, source).unwrap_err();
+        assert!(
+            matches!(err, RfmtError::PrismError(ref msg) if msg.contains("start offset is at 2:0"))
+        );
+    }
+
+    #[test]
+    fn test_end_offset_after_newline_is_next_line() {
+        // Heredoc terminators end just past their newline, which Prism reports as column 0
+        // of the following line
+        let source = "x = <<~A\n  a\nA\n";
+        let node = Node::new(
+            NodeType::LocalVariableWriteNode,
+            Location::new(1, 0, 4, 0, 0, 15),
+        );
+
+        assert!(validate_locations(&node, source).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_comment_location() {
+        let source = "foo # c\n";
+        let comment = crate::ast::Comment {
+            text: "# c".to_string(),
+            location: Location::new(1, 4, 1, 20, 4, 20),
+            comment_type: crate::ast::CommentType::Line,
+            position: crate::ast::CommentPosition::Trailing,
+        };
+        let node = node_at(NodeType::ProgramNode, source, 0, 3).with_comments(vec![comment]);
+
+        assert!(validate_locations(&node, source).is_err());
+    }
+}
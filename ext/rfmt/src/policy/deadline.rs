@@ -0,0 +1,84 @@
+use crate::error::{Result, RfmtError};
+use std::time::{Duration, Instant};
+
+/// Time budget for a single parse/format run
+///
+/// Long-running walks (AST conversion, emission) call `check` as they visit nodes
+/// so a slow or looping pass is abandoned instead of blocking the caller.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    started_at: Instant,
+    limit: Option<Duration>,
+}
+
+impl Deadline {
+    /// Create a deadline that expires `limit` after now
+    pub fn after(limit: Duration) -> Self {
+        Self {
+            started_at: Instant::now(),
+            limit: Some(limit),
+        }
+    }
+
+    /// Create a deadline that never expires
+    pub fn unlimited() -> Self {
+        Self {
+            started_at: Instant::now(),
+            limit: None,
+        }
+    }
+
+    /// Return `RfmtError::Timeout` once the time budget has been exceeded
+    pub fn check(&self) -> Result<()> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+
+        let elapsed = self.started_at.elapsed();
+        if elapsed > limit {
+            return Err(RfmtError::Timeout {
+                message: format!(
+                    "formatting took longer than {}ms (elapsed {}ms)",
+                    limit.as_millis(),
+                    elapsed.as_millis()
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Deadline {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_expires() {
+        let deadline = Deadline::unlimited();
+        assert!(deadline.check().is_ok());
+    }
+
+    #[test]
+    fn test_deadline_within_budget() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert!(deadline.check().is_ok());
+    }
+
+    #[test]
+    fn test_deadline_expired() {
+        let deadline = Deadline::after(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(2));
+
+        match deadline.check() {
+            Err(RfmtError::Timeout { message }) => assert!(message.contains("0ms")),
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+    }
+}
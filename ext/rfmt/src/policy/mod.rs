@@ -1,25 +1,58 @@
+pub mod deadline;
 pub mod validation;
 
-use crate::error::Result;
+use crate::error::{Result, RfmtError};
+use std::time::Duration;
+
+pub use deadline::Deadline;
 
 /// Security policy for rfmt operations
 #[derive(Debug, Clone)]
 pub struct SecurityPolicy {
     /// Maximum file size in bytes (default: 10MB)
     pub max_file_size: u64,
+
+    /// Maximum time a single parse/format run may take (default: 10s, `None` disables)
+    pub format_timeout: Option<Duration>,
 }
 
 impl SecurityPolicy {
     /// Validate source code size
-    pub fn validate_source_size(&self, source: &str) -> Result<()> {
+    pub fn validate_source_size(&self, source: &(impl AsRef<[u8]> + ?Sized)) -> Result<()> {
         validation::validate_source_size(source, self.max_file_size)
     }
+
+    /// Replace the time budget with `seconds`; an infinite value disables it
+    pub fn with_timeout_secs(mut self, seconds: f64) -> Result<Self> {
+        self.format_timeout = match Duration::try_from_secs_f64(seconds) {
+            Ok(limit) => Some(limit),
+            Err(_) if seconds == f64::INFINITY => None,
+            Err(_) => {
+                return Err(RfmtError::ConfigError {
+                    message: format!(
+                        "timeout must be a non-negative number of seconds, got {}",
+                        seconds
+                    ),
+                })
+            }
+        };
+        Ok(self)
+    }
+
+    /// Start the time budget for a parse/format run
+    pub fn deadline(&self) -> Deadline {
+        match self.format_timeout {
+            Some(limit) => Deadline::after(limit),
+            None => Deadline::unlimited(),
+        }
+    }
 }
 
 impl Default for SecurityPolicy {
     fn default() -> Self {
         Self {
             max_file_size: 10 * 1024 * 1024, // 10MB
+            format_timeout: Some(Duration::from_secs(10)),
         }
     }
 }
@@ -32,5 +65,31 @@ mod tests {
     fn test_default_policy() {
         let policy = SecurityPolicy::default();
         assert_eq!(policy.max_file_size, 10 * 1024 * 1024);
+        assert_eq!(policy.format_timeout, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_deadline_without_timeout() {
+        let policy = SecurityPolicy {
+            format_timeout: None,
+            ..SecurityPolicy::default()
+        };
+        assert!(policy.deadline().check().is_ok());
+    }
+
+    #[test]
+    fn test_with_timeout_secs() {
+        let policy = SecurityPolicy::default().with_timeout_secs(0.5).unwrap();
+        assert_eq!(policy.format_timeout, Some(Duration::from_millis(500)));
+
+        let policy = SecurityPolicy::default()
+            .with_timeout_secs(f64::INFINITY)
+            .unwrap();
+        assert_eq!(policy.format_timeout, None);
+
+        assert!(SecurityPolicy::default().with_timeout_secs(-1.0).is_err());
+        assert!(SecurityPolicy::default()
+            .with_timeout_secs(f64::NAN)
+            .is_err());
     }
 }
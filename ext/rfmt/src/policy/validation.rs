@@ -1,8 +1,8 @@
 use crate::error::{Result, RfmtError};
 
 /// Validate source code size
-pub fn validate_source_size(source: &str, max_size: u64) -> Result<()> {
-    let size = source.len() as u64;
+pub fn validate_source_size(source: &(impl AsRef<[u8]> + ?Sized), max_size: u64) -> Result<()> {
+    let size = source.as_ref().len() as u64;
 
     if size > max_size {
         return Err(RfmtError::UnsupportedFeature {
@@ -0,0 +1,214 @@
+use crate::error::{Result, RfmtError};
+use encoding_rs::{Encoding, UTF_8};
+
+/// Detect the source encoding from a magic comment, falling back to `default`
+///
+/// A UTF-8 byte order mark overrides both.
+///
+/// Like Ruby, only the first line (or the second, after a shebang) is considered,
+/// and any `coding:` / `coding=` form is accepted:
+/// `# encoding: Shift_JIS`, `# -*- coding: euc-jp -*-`, `# vim: fileencoding=cp932`
+pub fn detect_encoding(source: &[u8], default: &str) -> Result<&'static Encoding> {
+    if source.starts_with(UTF8_BOM) {
+        return Ok(UTF_8);
+    }
+
+    match magic_comment_encoding(source) {
+        Some(name) => lookup_encoding(&name),
+        None => lookup_encoding(default),
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Find the encoding name declared by a magic comment, if any
+fn magic_comment_encoding(source: &[u8]) -> Option<String> {
+    let mut lines = source.split(|&b| b == b'\n');
+    let first = lines.next()?;
+
+    let candidate = if first.starts_with(b"#!") {
+        lines.next()?
+    } else {
+        first
+    };
+
+    let line = String::from_utf8_lossy(candidate);
+    let comment = line.trim_start().strip_prefix('#')?;
+
+    let after = comment
+        .find("coding")
+        .map(|i| &comment[i + "coding".len()..])?;
+    let value = after.strip_prefix([':', '='])?.trim_start();
+    let name: String = value
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .collect();
+
+    (!name.is_empty()).then_some(name)
+}
+
+/// Resolve a Ruby encoding name to an ASCII-compatible `encoding_rs` encoding
+pub fn lookup_encoding(name: &str) -> Result<&'static Encoding> {
+    let normalized = name.trim().to_ascii_lowercase();
+
+    // Ruby-specific names that are not WHATWG labels
+    let label = match normalized.as_str() {
+        "us-ascii" | "ascii" | "ascii-8bit" | "binary" | "utf-8-mac" | "utf8-mac" => "utf-8",
+        "cp932" | "windows-31j" | "sjis-docomo" | "sjis-kddi" | "sjis-softbank" => "shift_jis",
+        "eucjp" | "eucjp-ms" | "euc-jis-2004" | "cp51932" => "euc-jp",
+        other => other,
+    };
+
+    match Encoding::for_label(label.as_bytes()) {
+        Some(encoding) if encoding.is_ascii_compatible() => Ok(encoding),
+        _ => Err(RfmtError::UnsupportedFeature {
+            feature: "Source encoding".to_string(),
+            explanation: format!("Encoding '{}' is not supported", name),
+        }),
+    }
+}
+
+/// Decode `source` to UTF-8, returning the text and, for non-UTF-8 encodings, the
+/// UTF-8 offset of every original byte offset (valid at character boundaries)
+pub fn decode(source: &[u8], encoding: &'static Encoding) -> Result<(String, Option<Vec<usize>>)> {
+    if encoding == UTF_8 {
+        let text = String::from_utf8(source.to_vec()).map_err(|e| {
+            RfmtError::FormatError(format!(
+                "Invalid UTF-8 byte sequence at offset {}",
+                e.utf8_error().valid_up_to()
+            ))
+        })?;
+        return Ok((text, None));
+    }
+
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(source.len() * 3 / 2);
+    let mut offsets = Vec::with_capacity(source.len() + 1);
+
+    // Feed one byte at a time so each character's start offset can be recorded
+    for (offset, byte) in source.iter().enumerate() {
+        offsets.push(text.len());
+        text.reserve(decoder.max_utf8_buffer_length(1).unwrap_or(16));
+
+        let (_, _, had_errors) =
+            decoder.decode_to_string(std::slice::from_ref(byte), &mut text, false);
+        if had_errors {
+            return Err(invalid_sequence(encoding, offset));
+        }
+    }
+
+    text.reserve(decoder.max_utf8_buffer_length(0).unwrap_or(16));
+    let (_, _, had_errors) = decoder.decode_to_string(&[], &mut text, true);
+    if had_errors {
+        return Err(invalid_sequence(encoding, source.len()));
+    }
+    offsets.push(text.len());
+
+    Ok((text, Some(offsets)))
+}
+
+/// Encode UTF-8 text back to `encoding`
+pub fn encode(text: &str, encoding: &'static Encoding) -> Result<Vec<u8>> {
+    if encoding == UTF_8 {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    let (bytes, _, had_unmappable) = encoding.encode(text);
+    if had_unmappable {
+        return Err(RfmtError::FormatError(format!(
+            "Formatted output contains characters that cannot be encoded in {}",
+            encoding.name()
+        )));
+    }
+
+    Ok(bytes.into_owned())
+}
+
+fn invalid_sequence(encoding: &'static Encoding, offset: usize) -> RfmtError {
+    RfmtError::FormatError(format!(
+        "Invalid {} byte sequence at offset {}",
+        encoding.name(),
+        offset
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{EUC_JP, SHIFT_JIS};
+
+    #[test]
+    fn test_detect_default_encoding() {
+        let encoding = detect_encoding(b"puts 1\n", "UTF-8").unwrap();
+        assert_eq!(encoding, UTF_8);
+    }
+
+    #[test]
+    fn test_detect_bom_overrides_magic_comment() {
+        let encoding = detect_encoding(b"\xEF\xBB\xBF# encoding: euc-jp\n", "Shift_JIS").unwrap();
+        assert_eq!(encoding, UTF_8);
+    }
+
+    #[test]
+    fn test_detect_magic_comment() {
+        let encoding = detect_encoding(b"# encoding: Shift_JIS\nputs 1\n", "UTF-8").unwrap();
+        assert_eq!(encoding, SHIFT_JIS);
+    }
+
+    #[test]
+    fn test_detect_emacs_style_after_shebang() {
+        let source = b"#!/usr/bin/env ruby\n# -*- coding: euc-jp -*-\nputs 1\n";
+        assert_eq!(detect_encoding(source, "UTF-8").unwrap(), EUC_JP);
+    }
+
+    #[test]
+    fn test_magic_comment_only_on_first_lines() {
+        let source = b"puts 1\n# encoding: Shift_JIS\n";
+        assert_eq!(detect_encoding(source, "UTF-8").unwrap(), UTF_8);
+    }
+
+    #[test]
+    fn test_ruby_encoding_aliases() {
+        assert_eq!(lookup_encoding("CP932").unwrap(), SHIFT_JIS);
+        assert_eq!(lookup_encoding("Windows-31J").unwrap(), SHIFT_JIS);
+        assert_eq!(lookup_encoding("eucJP-ms").unwrap(), EUC_JP);
+        assert_eq!(lookup_encoding("US-ASCII").unwrap(), UTF_8);
+    }
+
+    #[test]
+    fn test_unsupported_encoding() {
+        assert!(lookup_encoding("UTF-16LE").is_err());
+        assert!(lookup_encoding("klingon").is_err());
+    }
+
+    #[test]
+    fn test_decode_shift_jis_offsets() {
+        // "あ" is 2 bytes in Shift_JIS and 3 bytes in UTF-8
+        let (bytes, _, _) = SHIFT_JIS.encode("x = \"あ\"\n");
+        let (text, offsets) = decode(&bytes, SHIFT_JIS).unwrap();
+        let offsets = offsets.unwrap();
+
+        assert_eq!(text, "x = \"あ\"\n");
+        assert_eq!(offsets.len(), bytes.len() + 1);
+        // Closing quote: byte 7 in Shift_JIS, byte 8 in UTF-8
+        assert_eq!(offsets[7], 8);
+        assert_eq!(offsets[bytes.len()], text.len());
+    }
+
+    #[test]
+    fn test_decode_invalid_sequence() {
+        assert!(decode(b"x = \"\xff\xff\"", UTF_8).is_err());
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let (bytes, _, _) = EUC_JP.encode("# 日本語\n");
+        let (text, _) = decode(&bytes, EUC_JP).unwrap();
+        assert_eq!(encode(&text, EUC_JP).unwrap(), bytes.into_owned());
+    }
+
+    #[test]
+    fn test_encode_unmappable() {
+        assert!(encode("😀", SHIFT_JIS).is_err());
+    }
+}
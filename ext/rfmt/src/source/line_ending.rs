@@ -0,0 +1,121 @@
+use crate::config::EndOfLine;
+
+/// UTF-8 byte order mark
+pub const BOM: char = '\u{FEFF}';
+
+/// Line ending style detected in or written to a source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Detect the line ending from the first line break; files without one use LF
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(i) if i > 0 && text.as_bytes()[i - 1] == b'\r' => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    /// Resolve the configured style against the ending detected in the input
+    pub fn resolve(end_of_line: &EndOfLine, detected: LineEnding) -> Self {
+        match end_of_line {
+            EndOfLine::Lf => LineEnding::Lf,
+            EndOfLine::Crlf => LineEnding::Crlf,
+            EndOfLine::Auto => detected,
+        }
+    }
+
+    /// Apply this ending to LF-normalized text
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Strip a leading BOM and turn every CRLF into LF so the emitter only ever sees `\n`
+///
+/// Returns the normalized text and, when anything was removed, the normalized offset
+/// of every offset into `text` (removed bytes map to where the next byte ends up).
+pub fn normalize(text: &str) -> (String, Option<Vec<usize>>) {
+    let body = text.strip_prefix(BOM).unwrap_or(text);
+    let skipped = text.len() - body.len();
+
+    if skipped == 0 && !body.contains("\r\n") {
+        return (text.to_string(), None);
+    }
+
+    let bytes = body.as_bytes();
+    let mut offsets = vec![0; skipped];
+    offsets.reserve(body.len() + 1);
+
+    let mut removed = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        offsets.push(i - removed);
+        if byte == b'\r' && bytes.get(i + 1) == Some(&b'\n') {
+            removed += 1;
+        }
+    }
+    offsets.push(bytes.len() - removed);
+
+    let normalized = body.replace("\r\n", "\n");
+    (normalized, Some(offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_resolve_line_ending() {
+        assert_eq!(
+            LineEnding::resolve(&EndOfLine::Auto, LineEnding::Crlf),
+            LineEnding::Crlf
+        );
+        assert_eq!(
+            LineEnding::resolve(&EndOfLine::Lf, LineEnding::Crlf),
+            LineEnding::Lf
+        );
+        assert_eq!(
+            LineEnding::resolve(&EndOfLine::Crlf, LineEnding::Lf),
+            LineEnding::Crlf
+        );
+    }
+
+    #[test]
+    fn test_normalize_plain_text_is_untouched() {
+        assert_eq!(normalize("a\nb\n"), ("a\nb\n".to_string(), None));
+    }
+
+    #[test]
+    fn test_normalize_crlf() {
+        let (text, offsets) = normalize("ab\r\nc\r\n");
+        assert_eq!(text, "ab\nc\n");
+        assert_eq!(offsets.unwrap(), vec![0, 1, 2, 2, 3, 4, 4, 5]);
+    }
+
+    #[test]
+    fn test_normalize_keeps_lone_cr() {
+        let (text, offsets) = normalize("a\rb\r\n");
+        assert_eq!(text, "a\rb\n");
+        assert_eq!(offsets.unwrap(), vec![0, 1, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn test_normalize_strips_bom() {
+        let (text, offsets) = normalize("\u{FEFF}x\r\n");
+        assert_eq!(text, "x\n");
+        assert_eq!(offsets.unwrap(), vec![0, 0, 0, 0, 1, 1, 2]);
+    }
+}
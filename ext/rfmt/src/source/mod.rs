@@ -0,0 +1,203 @@
+pub mod encoding;
+pub mod line_ending;
+
+use crate::ast::{Location, Node};
+use crate::config::EndOfLine;
+use crate::error::Result;
+use encoding_rs::Encoding;
+use line_ending::{LineEnding, BOM};
+
+/// Ruby source decoded to UTF-8 for formatting
+///
+/// Prism reports byte offsets in the file's own encoding while the emitter works on
+/// a UTF-8 `String` with LF line endings and no BOM. `SourceText` keeps the offset
+/// mapping between the two so node locations can be translated, and restores the
+/// encoding, BOM and line endings on the formatted output.
+pub struct SourceText {
+    text: String,
+    encoding: &'static Encoding,
+    bom: bool,
+    line_ending: LineEnding,
+    /// UTF-8 offset for each original byte offset; `None` when no translation is needed
+    offsets: Option<Vec<usize>>,
+}
+
+impl SourceText {
+    /// Decode raw source bytes, honoring a magic comment before `default_encoding`
+    pub fn decode(source: &[u8], default_encoding: &str) -> Result<Self> {
+        let encoding = encoding::detect_encoding(source, default_encoding)?;
+        let (decoded, decode_offsets) = encoding::decode(source, encoding)?;
+
+        let bom = decoded.starts_with(BOM);
+        let line_ending = LineEnding::detect(&decoded);
+        let (text, normalize_offsets) = line_ending::normalize(&decoded);
+
+        let offsets = match (decode_offsets, normalize_offsets) {
+            (Some(decoded), Some(normalized)) => Some(
+                decoded
+                    .into_iter()
+                    .map(|offset| normalized[offset])
+                    .collect(),
+            ),
+            (decoded, normalized) => decoded.or(normalized),
+        };
+
+        Ok(Self {
+            text,
+            encoding,
+            bom,
+            line_ending,
+            offsets,
+        })
+    }
+
+    /// The decoded UTF-8 source
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Translate every node and comment location from original byte offsets to
+    /// offsets into `text()`
+    pub fn remap_locations(&self, ast: &mut Node) {
+        let Some(offsets) = &self.offsets else {
+            return;
+        };
+
+        let mut stack = vec![ast];
+        while let Some(node) = stack.pop() {
+            remap_location(&mut node.location, offsets);
+            for comment in &mut node.comments {
+                remap_location(&mut comment.location, offsets);
+            }
+            stack.extend(node.children.iter_mut());
+        }
+    }
+
+    /// Encode formatted output back to the source's original encoding, restoring
+    /// the BOM and applying the configured line ending
+    pub fn encode(&self, formatted: &str, end_of_line: &EndOfLine) -> Result<Vec<u8>> {
+        let mut output = LineEnding::resolve(end_of_line, self.line_ending).apply(formatted);
+        if self.bom {
+            output.insert(0, BOM);
+        }
+
+        encoding::encode(&output, self.encoding)
+    }
+}
+
+/// Map a location through `offsets`, leaving out-of-range offsets for validation to report
+fn remap_location(location: &mut Location, offsets: &[usize]) {
+    let map = |offset: usize| offsets.get(offset).copied().unwrap_or(offset);
+
+    let start_line_offset = location.start_offset.saturating_sub(location.start_column);
+    let end_line_offset = location.end_offset.saturating_sub(location.end_column);
+
+    location.start_column = map(location.start_offset).saturating_sub(map(start_line_offset));
+    location.end_column = map(location.end_offset).saturating_sub(map(end_line_offset));
+    location.start_offset = map(location.start_offset);
+    location.end_offset = map(location.end_offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::NodeType;
+    use encoding_rs::SHIFT_JIS;
+
+    #[test]
+    fn test_utf8_source_is_untouched() {
+        let source = SourceText::decode("puts \"あ\"\n".as_bytes(), "UTF-8").unwrap();
+        let mut node = Node::new(NodeType::CallNode, Location::new(1, 0, 1, 10, 0, 10));
+        source.remap_locations(&mut node);
+
+        assert_eq!(source.text(), "puts \"あ\"\n");
+        assert_eq!(node.location, Location::new(1, 0, 1, 10, 0, 10));
+    }
+
+    #[test]
+    fn test_shift_jis_locations_are_remapped() {
+        let original = "# encoding: Shift_JIS\nx = \"あい\"\ny = 1\n";
+        let (bytes, _, _) = SHIFT_JIS.encode(original);
+        let source = SourceText::decode(&bytes, "UTF-8").unwrap();
+
+        // `y = 1` starts at byte 33 in Shift_JIS (22 + 11) and 35 in UTF-8
+        let mut node = Node::new(NodeType::ProgramNode, Location::new(2, 0, 3, 5, 22, 38))
+            .with_children(vec![
+                Node::new(NodeType::StringNode, Location::new(2, 4, 2, 10, 26, 32)),
+                Node::new(
+                    NodeType::LocalVariableWriteNode,
+                    Location::new(3, 0, 3, 5, 33, 38),
+                ),
+            ]);
+        source.remap_locations(&mut node);
+
+        assert_eq!(source.text(), original);
+        assert_eq!(node.location, Location::new(2, 0, 3, 5, 22, 40));
+        assert_eq!(
+            node.children[0].location,
+            Location::new(2, 4, 2, 12, 26, 34)
+        );
+        assert_eq!(node.children[1].location, Location::new(3, 0, 3, 5, 35, 40));
+    }
+
+    #[test]
+    fn test_encode_back_to_original_encoding() {
+        let original = "# encoding: Shift_JIS\nputs \"あ\"\n";
+        let (bytes, _, _) = SHIFT_JIS.encode(original);
+        let source = SourceText::decode(&bytes, "UTF-8").unwrap();
+
+        assert_eq!(
+            source.encode(source.text(), &EndOfLine::Auto).unwrap(),
+            bytes.into_owned()
+        );
+    }
+
+    #[test]
+    fn test_crlf_and_bom_are_normalized() {
+        let source = SourceText::decode("\u{FEFF}x = 1\r\ny = 2\r\n".as_bytes(), "UTF-8").unwrap();
+
+        // `y = 2` starts at byte 10 with the BOM and CRLF, 6 without
+        let mut node = Node::new(
+            NodeType::LocalVariableWriteNode,
+            Location::new(2, 0, 2, 5, 10, 15),
+        );
+        source.remap_locations(&mut node);
+
+        assert_eq!(source.text(), "x = 1\ny = 2\n");
+        assert_eq!(node.location, Location::new(2, 0, 2, 5, 6, 11));
+    }
+
+    #[test]
+    fn test_encode_restores_bom_and_line_endings() {
+        let source = SourceText::decode("\u{FEFF}x = 1\r\n".as_bytes(), "UTF-8").unwrap();
+
+        assert_eq!(
+            source.encode("x = 1\n", &EndOfLine::Auto).unwrap(),
+            "\u{FEFF}x = 1\r\n".as_bytes()
+        );
+        assert_eq!(
+            source.encode("x = 1\n", &EndOfLine::Lf).unwrap(),
+            "\u{FEFF}x = 1\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_crlf_in_shift_jis_source() {
+        let original = "# encoding: Shift_JIS\r\nx = \"あ\"\r\ny = 1\r\n";
+        let (bytes, _, _) = SHIFT_JIS.encode(original);
+        let source = SourceText::decode(&bytes, "UTF-8").unwrap();
+
+        // `y = 1` starts at byte 33 in Shift_JIS with CRLF and 32 in UTF-8 with LF
+        let mut node = Node::new(
+            NodeType::LocalVariableWriteNode,
+            Location::new(3, 0, 3, 5, 33, 38),
+        );
+        source.remap_locations(&mut node);
+
+        assert_eq!(node.location, Location::new(3, 0, 3, 5, 32, 37));
+        assert_eq!(
+            source.encode(source.text(), &EndOfLine::Auto).unwrap(),
+            bytes.into_owned()
+        );
+    }
+}
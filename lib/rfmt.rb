@@ -13,17 +13,39 @@ module Rfmt
   class RfmtError < Error; end
   # AST validation errors
   class ValidationError < RfmtError; end
+  # Rust panics caught at the native boundary (always a bug in rfmt)
+  class InternalError < RfmtError; end
+  # Parsing or formatting ran out of its time budget
+  class TimeoutError < RfmtError; end
+
+  # A problem found in the source while formatting it, such as a gem declared
+  # twice in a Gemfile. Lines are 1-based and columns 0-based, like Prism's.
+  Diagnostic = Struct.new(:message, :start_line, :start_column, :end_line, :end_column)
 
   # Format Ruby source code
   # @param source [String] Ruby source code to format
+  # @param path [String, nil] File the source came from; `Gemfile`, `gems.rb` and
+  #   `*.gemspec` get the Gemfile rules
+  # @param timeout [Numeric, nil] Seconds the run may take before raising
+  #   {TimeoutError} (default: 10, `Float::INFINITY` disables the limit)
   # @return [String] Formatted Ruby code
-  def self.format(source)
+  def self.format(source, path: nil, timeout: nil)
+    format_with_diagnostics(source, path: path, timeout: timeout).first
+  end
+
+  # Format Ruby source code and report the problems found in it
+  # @param source [String] Ruby source code to format
+  # @param path [String, nil] File the source came from, as for {format}
+  # @param timeout [Numeric, nil] Time budget in seconds, as for {format}
+  # @return [Array(String, Array<Diagnostic>)] Formatted Ruby code and diagnostics
+  def self.format_with_diagnostics(source, path: nil, timeout: nil)
     # Step 1: Parse with Prism (Ruby side)
     prism_json = PrismBridge.parse(source)
 
     # Step 2: Format in Rust
     # Pass both source and AST to enable source extraction fallback
-    format_code(source, prism_json)
+    formatted, diagnostics = format_code_with_diagnostics(source, prism_json, path.to_s, timeout&.to_f)
+    [formatted, diagnostics.map { |fields| Diagnostic.new(*fields) }]
   rescue PrismBridge::ParseError => e
     # Re-raise with more context
     raise Error, "Failed to parse Ruby code: #{e.message}"
@@ -39,7 +61,7 @@ module Rfmt
   # @return [String] Formatted Ruby code
   def self.format_file(path)
     source = File.read(path)
-    format(source)
+    format(source, path: path)
   rescue Errno::ENOENT
     raise Error, "File not found: #{path}"
   end
@@ -255,7 +255,7 @@ module Rfmt
       start_time = Time.now
       source = File.read(file)
 
-      formatted = Rfmt.format(source)
+      formatted, diagnostics = Rfmt.format_with_diagnostics(source, path: file)
       changed = source != formatted
 
       {
@@ -263,6 +263,7 @@ module Rfmt
         changed: changed,
         original: source,
         formatted: formatted,
+        diagnostics: diagnostics,
         duration: Time.now - start_time,
         error: nil
       }
@@ -286,6 +287,7 @@ module Rfmt
       stats = { changed: 0, errors: 0, failed: 0, duration: 0 }
 
       results.each do |result|
+        report_diagnostics(result)
         if result[:error]
           handle_error_result(result, stats)
         elsif result[:changed]
@@ -298,6 +300,13 @@ module Rfmt
       stats
     end
 
+    def report_diagnostics(result)
+      (result[:diagnostics] || []).each do |diagnostic|
+        location = "#{result[:file]}:#{diagnostic.start_line}:#{diagnostic.start_column + 1}"
+        say_error "#{location}: #{diagnostic.message}", :yellow
+      end
+    end
+
     def handle_error_result(result, stats)
       say "Error in #{result[:file]}: #{result[:error]}", :red
       stats[:errors] += 1
@@ -63,7 +63,8 @@ module Rfmt
     def self.serialize_ast_with_comments(result)
       comments = result.comments.map do |comment|
         {
-          comment_type: comment.class.name.split('::').last.downcase.gsub('comment', ''),
+          # `=begin`/`=end` comments are `EmbDocComment`s, everything else is a `#` line
+          type: comment.is_a?(Prism::EmbDocComment) ? 'block' : 'line',
           location: {
             start_line: comment.location.start_line,
             start_column: comment.location.start_column,
@@ -72,8 +73,8 @@ module Rfmt
             start_offset: comment.location.start_offset,
             end_offset: comment.location.end_offset
           },
-          text: comment.location.slice,
-          position: 'leading' # Default position, will be refined by Rust
+          text: utf8(comment.location.slice),
+          position: 'leading' # Placeholder, assigned by the attachment pass in Rust
         }
       end
 
@@ -87,10 +88,12 @@ module Rfmt
     def self.convert_node(node)
       return nil if node.nil?
 
+      children = extract_children(node)
+
       {
         node_type: node_type_name(node),
-        location: extract_location(node),
-        children: extract_children(node),
+        location: cover_children(extract_location(node), children),
+        children: children,
         metadata: extract_metadata(node),
         comments: extract_comments(node),
         formatting: extract_formatting(node)
@@ -117,11 +120,11 @@ module Rfmt
 
       # Check this node's closing_loc
       if node.respond_to?(:closing_loc) && node.closing_loc
-        closing = node.closing_loc
-        if closing.end_offset > end_offset
-          end_offset = closing.end_offset
-          end_line = closing.end_line
-          end_column = closing.end_column
+        closing_offset, closing_line, closing_column = closing_end(node.closing_loc)
+        if closing_offset > end_offset
+          end_offset = closing_offset
+          end_line = closing_line
+          end_column = closing_column
         end
       end
 
@@ -129,12 +132,12 @@ module Rfmt
       node.child_nodes.compact.each do |child|
         next unless child.respond_to?(:closing_loc) && child.closing_loc
 
-        closing = child.closing_loc
-        next unless closing.end_offset > end_offset
+        closing_offset, closing_line, closing_column = closing_end(child.closing_loc)
+        next unless closing_offset > end_offset
 
-        end_offset = closing.end_offset
-        end_line = closing.end_line
-        end_column = closing.end_column
+        end_offset = closing_offset
+        end_line = closing_line
+        end_column = closing_column
       end
 
       {
@@ -147,6 +150,40 @@ module Rfmt
       }
     end
 
+    # End of a closing location as [offset, line, column]
+    # A heredoc terminator's location includes its newline; stop before it so the node
+    # ends on the terminator line and the line break stays between statements
+    def self.closing_end(closing)
+      newline = closing.slice[/\r?\n\z/]
+      return [closing.end_offset, closing.end_line, closing.end_column] unless newline
+
+      length = closing.slice.bytesize - newline.bytesize
+      [closing.start_offset + length, closing.start_line, closing.start_column + length]
+    end
+
+    # Widen a location so it contains all of its converted children
+    # A heredoc nested a few calls deep (e.g. `foo(bar(<<~TEXT))`) stretches the inner
+    # node past the closing identifier, and every ancestor has to cover it as well
+    def self.cover_children(location, children)
+      children.each do |child|
+        child_loc = child[:location]
+
+        if child_loc[:start_offset] < location[:start_offset]
+          location[:start_offset] = child_loc[:start_offset]
+          location[:start_line] = child_loc[:start_line]
+          location[:start_column] = child_loc[:start_column]
+        end
+
+        next unless child_loc[:end_offset] > location[:end_offset]
+
+        location[:end_offset] = child_loc[:end_offset]
+        location[:end_line] = child_loc[:end_line]
+        location[:end_column] = child_loc[:end_column]
+      end
+
+      location
+    end
+
     # Extract child nodes
     def self.extract_children(node)
       children = []
@@ -195,12 +232,11 @@ module Rfmt
                    when Prism::HashNode
                      node.elements || []
                    when Prism::BlockNode
-                     params = if node.parameters
-                                node.parameters.child_nodes.compact
-                              else
-                                []
-                              end
-                     params + [node.body].compact
+                     # BlockParametersNode, or NumberedParametersNode/ItParametersNode for `_1` and `it`
+                     [node.parameters, node.body].compact
+                   when Prism::BlockParametersNode
+                     params = node.parameters ? node.parameters.child_nodes.compact : []
+                     params + node.locals
                    when Prism::BeginNode
                      [
                        node.statements,
@@ -224,9 +260,11 @@ module Rfmt
                      result << node.statements if node.statements
                      result << node.subsequent if node.subsequent
                      result
+                   when Prism::OptionalParameterNode, Prism::OptionalKeywordParameterNode
+                     [node.value].compact
                    when Prism::SymbolNode, Prism::LocalVariableReadNode, Prism::InstanceVariableReadNode
                      []
-                   when Prism::LocalVariableWriteNode, Prism::InstanceVariableWriteNode
+                   when Prism::LocalVariableWriteNode, Prism::InstanceVariableWriteNode, Prism::ConstantWriteNode
                      [node.value].compact
                    when Prism::ReturnNode
                      node.arguments ? node.arguments.child_nodes.compact : []
@@ -409,6 +447,8 @@ module Rfmt
           metadata['parameters_text'] = node.parameters.location.slice
           metadata['has_parens'] = (!node.lparen_loc.nil?).to_s
         end
+        # Endless methods (`def name = expr`) have an `=` and no `end` keyword
+        metadata['endless'] = (!node.equal_loc.nil?).to_s
         # Check if this is a class method (def self.method_name)
         if node.respond_to?(:receiver) && node.receiver
           receiver = node.receiver
@@ -425,10 +465,27 @@ module Rfmt
         if (message = extract_message_name(node))
           metadata['message'] = message
         end
+        # `.` or `&.` between the receiver and the message, used to lay out method chains
+        metadata['call_operator'] = node.call_operator_loc.slice if node.call_operator_loc
+        # Whether the arguments are wrapped in parentheses, which decides what a block binds to
+        metadata['has_parens'] = (!node.opening_loc.nil?).to_s if node.arguments
+        if (operator = extract_binary_operator(node))
+          metadata['binary_operator'] = operator
+        elsif (operator = extract_unary_operator(node))
+          metadata['unary_operator'] = operator
+        end
+      when Prism::AndNode, Prism::OrNode
+        # `&&`/`and` and `||`/`or` bind differently, so keep the spelling
+        metadata['operator'] = node.operator_loc.slice
       when Prism::StringNode
         if (content = extract_string_content(node))
           metadata['content'] = content
         end
+        # `'`, `"`, `%q(` or `<<~SQL`; words inside `%w[]` have no opening
+        metadata['opening'] = node.opening_loc.slice if node.opening_loc
+      when Prism::InterpolatedStringNode, Prism::ArrayNode
+        # `"`, `%Q{`, `%w[`, `%i(` or `[`; implicit arrays (`a = 1, 2`) have no opening
+        metadata['opening'] = node.opening_loc.slice if node.opening_loc
       when Prism::IntegerNode
         if (value = extract_literal_value(node))
           metadata['value'] = value
@@ -441,12 +498,50 @@ module Rfmt
         if (value = extract_literal_value(node))
           metadata['value'] = value
         end
+      when Prism::RequiredParameterNode, Prism::OptionalParameterNode, Prism::RestParameterNode,
+           Prism::RequiredKeywordParameterNode, Prism::OptionalKeywordParameterNode,
+           Prism::KeywordRestParameterNode, Prism::BlockParameterNode, Prism::BlockLocalVariableNode
+        # Anonymous `*`, `**` and `&` parameters have no name
+        metadata['name'] = node.name.to_s if node.name
+      when Prism::ArrayPatternNode, Prism::HashPatternNode, Prism::FindPatternNode
+        # `Point[x, y]`, `Config(db:)` or bracketless top-level patterns like `in a, *rest`
+        metadata['constant'] = node.constant.slice if node.constant
+        metadata['opening'] = node.opening_loc.slice if node.opening_loc
       when Prism::IfNode, Prism::UnlessNode
         # Detect ternary operator: if_keyword_loc is nil for ternary
         metadata['is_ternary'] = node.if_keyword_loc.nil?.to_s if node.respond_to?(:if_keyword_loc)
       end
 
-      metadata
+      if (style = extract_heredoc_style(node))
+        metadata['heredoc'] = style
+      end
+
+      metadata.transform_values { |value| utf8(value) }
+    end
+
+    # Heredoc style from the opener: `<<~` is squiggly, `<<-` is dash, bare `<<` is plain
+    def self.extract_heredoc_style(node)
+      return nil unless node.respond_to?(:opening_loc) && node.opening_loc
+
+      opening = node.opening_loc.slice
+      return nil unless opening.start_with?('<<')
+
+      case opening[2]
+      when '~' then 'squiggly'
+      when '-' then 'dash'
+      else 'plain'
+      end
+    end
+
+    # Source slices keep the source encoding (e.g. Shift_JIS), but JSON needs UTF-8.
+    # The Rust side decodes the source itself, so these values are informational.
+    def self.utf8(value)
+      return value unless value.is_a?(String)
+      return value if value.encoding == Encoding::UTF_8 && value.valid_encoding?
+
+      value.encode(Encoding::UTF_8)
+    rescue EncodingError
+      value.dup.force_encoding(Encoding::UTF_8).scrub
     end
 
     # Extract comments associated with the node
@@ -5,6 +5,12 @@ module Rfmt
   # This module encapsulates the logic for accessing Prism node properties,
   # making the code resilient to Prism API changes
   module PrismNodeExtractor
+    # Methods that Ruby parses as binary operators when written without a dot
+    BINARY_OPERATORS = %i[** * / % + - << >> & | ^ < <= > >= == != === =~ !~ <=>].freeze
+
+    # Methods that Ruby parses as prefix operators (`!x`, `not x`, `-x`, `+x`, `~x`)
+    UNARY_OPERATORS = %i[! -@ +@ ~].freeze
+
     # Extract the name from a node
     # @param node [Prism::Node] The node to extract name from
     # @return [String, nil] The node name or nil if not available
@@ -94,6 +100,35 @@ module Rfmt
       call_node.message.to_s
     end
 
+    # Extract the operator of a binary operator call such as `a + b`
+    # @param call_node [Prism::CallNode] The call node
+    # @return [String, nil] The operator, or nil for ordinary calls like `a.+(b)`
+    def extract_binary_operator(call_node)
+      return nil unless operator_call?(call_node) && BINARY_OPERATORS.include?(call_node.name)
+
+      arguments = call_node.arguments&.arguments
+      return nil unless arguments&.size == 1
+
+      call_node.name.to_s
+    end
+
+    # Extract the operator of a prefix operator call such as `!a` or `not a`
+    # @param call_node [Prism::CallNode] The call node
+    # @return [String, nil] The operator as written (`!`, `not`, `-`, `+` or `~`)
+    def extract_unary_operator(call_node)
+      return nil unless operator_call?(call_node) && UNARY_OPERATORS.include?(call_node.name)
+      return nil if call_node.arguments
+
+      call_node.message.to_s
+    end
+
+    # Whether a call is written in operator form: a receiver, no dot, no parentheses
+    # around the arguments and no block
+    def operator_call?(call_node)
+      call_node.receiver && call_node.call_operator_loc.nil? && call_node.opening_loc.nil? &&
+        call_node.block.nil? && call_node.message_loc
+    end
+
     # Extract content from a string node
     # @param string_node [Prism::StringNode] The string node
     # @return [String, nil] The string content or nil if not available
@@ -5,22 +5,53 @@ require 'rfmt'
 module RubyLsp
   module Rfmt
     class FormatterRunner
+      # Seconds a formatting request may take. The editor sends a new request as the
+      # user keeps typing, so a slow one is abandoned rather than waited for.
+      DEFAULT_TIMEOUT = 2
+
+      # @param timeout [Numeric] Time budget for each formatting request
+      def initialize(timeout: DEFAULT_TIMEOUT)
+        @timeout = timeout
+      end
+
       # @param uri [URI::Generic] Document URI
       # @param document [RubyLsp::RubyDocument] Target document
       # @return [String, nil] Formatted text or nil on error
-      def run_formatting(_uri, document)
+      def run_formatting(uri, document)
         source = document.source
-        ::Rfmt.format(source)
+        ::Rfmt.format(source, path: uri.path, timeout: @timeout)
       rescue ::Rfmt::Error
         nil
       end
 
       # @param uri [URI::Generic] Document URI
       # @param document [RubyLsp::RubyDocument] Target document
-      # @return [Array<RubyLsp::Interface::Diagnostic>]
-      def run_diagnostic(_uri, _document)
+      # @return [Array<RubyLsp::Interface::Diagnostic>] Problems such as duplicate
+      #   gems, or none when the document cannot be formatted
+      def run_diagnostic(uri, document)
+        _, diagnostics = ::Rfmt.format_with_diagnostics(document.source, path: uri.path, timeout: @timeout)
+        diagnostics.map { |diagnostic| to_lsp_diagnostic(diagnostic) }
+      rescue ::Rfmt::Error
         []
       end
+
+      private
+
+      # @param diagnostic [Rfmt::Diagnostic]
+      # @return [RubyLsp::Interface::Diagnostic]
+      def to_lsp_diagnostic(diagnostic)
+        ::RubyLsp::Interface::Diagnostic.new(
+          range: ::RubyLsp::Interface::Range.new(
+            start: ::RubyLsp::Interface::Position.new(
+              line: diagnostic.start_line - 1, character: diagnostic.start_column
+            ),
+            end: ::RubyLsp::Interface::Position.new(line: diagnostic.end_line - 1, character: diagnostic.end_column)
+          ),
+          severity: ::RubyLsp::Constant::DiagnosticSeverity::WARNING,
+          source: 'rfmt',
+          message: diagnostic.message
+        )
+      end
     end
   end
 end
@@ -0,0 +1,71 @@
+# frozen_string_literal: true
+
+require 'spec_helper'
+
+RSpec.describe Rfmt, 'Block Parameters Formatting' do
+  it 'normalizes spacing between block parameters' do
+    source = <<~RUBY
+      pairs.each do | key,value |
+        puts key
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(<<~RUBY)
+      pairs.each do |key, value|
+        puts key
+      end
+    RUBY
+  end
+
+  it 'keeps nested destructuring' do
+    source = <<~RUBY
+      rows.each do |id, (name,( first, *rest ))|
+        puts name
+      end
+    RUBY
+    expect(Rfmt.format(source)).to include('rows.each do |id, (name, (first, *rest))|')
+  end
+
+  it 'keeps block-local variables and empty parameters' do
+    source = <<~RUBY
+      items.each do |item;total|
+        total = item
+      end
+      retry_on do ||
+        run
+      end
+    RUBY
+    result = Rfmt.format(source)
+    expect(result).to include('items.each do |item; total|')
+    expect(result).to include('retry_on do ||')
+  end
+
+  it 'keeps defaults containing a pipe' do
+    source = <<~RUBY
+      handlers.each do |handler, mask = (READ | WRITE)|
+        handler.call(mask)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to include('handlers.each do |handler, mask = (READ | WRITE)|')
+  end
+
+  it 'keeps the trailing comma of an implicit rest' do
+    source = <<~RUBY
+      pairs.map do |first,|
+        first
+      end
+    RUBY
+    expect(Rfmt.format(source)).to include('pairs.map do |first,|')
+  end
+
+  it 'emits no parameters for numbered parameters and it' do
+    source = <<~RUBY
+      users.each do
+        puts _1
+      end
+      users.each do
+        puts it
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+end
@@ -19,4 +19,43 @@ RSpec.describe Rfmt, 'Case/When Formatting' do
     expect(result).to include('case value')
     expect(result).to include('when Integer')
   end
+
+  it 'keeps one-line when branches by default' do
+    source = <<~RUBY
+      case status
+      when :pending then process_pending
+      when :done then archive
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+
+  it 'puts the body of multi-line in branches on their own lines' do
+    source = <<~RUBY
+      case data
+      in { name: String => name }
+        greet(name)
+      in []
+        skip
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+
+  it 'wraps long when condition lists at commas' do
+    source = <<~RUBY
+      case token
+      when :plus, :minus, :star, :slash, :percent, :caret, :ampersand, :pipe, :tilde, :bang, :question, :colon, :semicolon
+        operator(token)
+      end
+    RUBY
+    expected = <<~RUBY
+      case token
+      when :plus, :minus, :star, :slash, :percent, :caret, :ampersand, :pipe, :tilde, :bang, :question,
+           :colon, :semicolon
+        operator(token)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
 end
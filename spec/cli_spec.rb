@@ -28,5 +28,24 @@ RSpec.describe Rfmt::CLI do
         expect { cli.format([file.path]) }.not_to raise_error
       end
     end
+
+    it 'reports diagnostics with the file, line and column' do
+      require 'tmpdir'
+
+      Dir.mktmpdir do |dir|
+        Dir.chdir(dir) do
+          File.write('rfmt.yml', <<~YAML)
+            version: "1.0"
+            formatting:
+              gemfile:
+                check_duplicates: true
+          YAML
+          File.write('Gemfile', "gem 'rails'\ngem 'rails'\n")
+
+          expect { cli.format(['Gemfile']) }
+            .to output(/Gemfile:2:1: gem 'rails' is already declared on line 1/).to_stderr
+        end
+      end
+    end
   end
 end
@@ -168,4 +168,1205 @@ RSpec.describe 'Config-based formatting' do
       expect(formatted).to include("\t\tputs \"not positive\"")
     end
   end
+
+  describe 'line endings and BOM' do
+    let(:crlf_source) { "class User\r\ndef name\r\n\"a\r\nb\"\r\nend\r\nend\r\n" }
+
+    it 'keeps CRLF line endings everywhere by default' do
+      formatted = Rfmt.format(crlf_source)
+
+      expect(formatted).to eq("class User\r\n  def name\r\n    \"a\r\nb\"\r\n  end\r\nend\r\n")
+    end
+
+    it 'converts line endings to end_of_line from rfmt.yml' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          end_of_line: "lf"
+      YAML
+
+      formatted = Rfmt.format(crlf_source)
+
+      expect(formatted).not_to include("\r")
+      expect(formatted).to eq("class User\n  def name\n    \"a\nb\"\n  end\nend\n")
+    end
+
+    it 'preserves a leading UTF-8 BOM' do
+      formatted = Rfmt.format("\uFEFFclass User\nend\n")
+
+      expect(formatted).to eq("\uFEFFclass User\nend\n")
+    end
+  end
+
+  describe 'method style' do
+    let(:methods_source) do
+      <<~RUBY
+        class Point
+        def x = @x
+        def origin?; x.zero?; end
+        def to_s
+        format("(%d)", x)
+        end
+        def reset!; end
+        end
+      RUBY
+    end
+
+    it 'preserves endless and one-line methods by default' do
+      expect(Rfmt.format(methods_source)).to eq(<<~RUBY)
+        class Point
+          def x = @x
+          def origin?; x.zero?; end
+          def to_s
+            format("(%d)", x)
+          end
+          def reset!; end
+        end
+      RUBY
+    end
+
+    it 'expands every method when method_style is expanded' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            method_style: "expanded"
+      YAML
+
+      expect(Rfmt.format(methods_source)).to eq(<<~RUBY)
+        class Point
+          def x
+            @x
+          end
+          def origin?
+            x.zero?
+          end
+          def to_s
+            format("(%d)", x)
+          end
+          def reset!
+          end
+        end
+      RUBY
+    end
+
+    it 'converts single-expression methods when method_style is endless' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            method_style: "endless"
+      YAML
+      source = <<~RUBY
+        def area(width, height)
+          width * height
+        end
+
+        def name=(value)
+          @name = value
+        end
+
+        def valid?
+          return false if @name.nil?
+        end
+      RUBY
+
+      formatted = Rfmt.format(source)
+
+      expect(formatted).to include('def area(width, height) = width * height')
+      expect(formatted).to include("def name=(value)\n  @name = value\nend")
+      expect(formatted).to include("def valid?\n  return false if @name.nil?\nend")
+      expect(Prism.parse(formatted).errors).to be_empty
+    end
+
+    it 'keeps statements that are not expressions out of endless methods' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            method_style: "endless"
+      YAML
+      source = <<~RUBY
+        def owner
+          return @owner
+        end
+
+        def rename
+          alias to_str to_s
+        end
+
+        def forget
+          undef to_str
+        end
+      RUBY
+
+      formatted = Rfmt.format(source)
+
+      expect(formatted).to eq(source)
+      expect(Prism.parse(formatted).errors).to be_empty
+    end
+  end
+
+  describe 'block delimiters' do
+    def write_block_delimiters(value)
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            block_delimiters: "#{value}"
+      YAML
+    end
+
+    it 'keeps the delimiters from the source by default' do
+      source = "items.each do |item|\n  puts item\nend\nnames = items.map { |item| item.name }\n"
+
+      expect(Rfmt.format(source)).to eq(source)
+    end
+
+    it 'collapses short blocks into braces and expands long ones with line_count_based' do
+      write_block_delimiters('line_count_based')
+      source = <<~RUBY
+        items.each do |item|
+          puts item
+        end
+        items.each { |item| puts item; log(item, "processed by the nightly reconciliation job", level: :debug) }
+      RUBY
+
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        items.each { |item| puts item }
+        items.each do |item|
+          puts item
+          log(item, "processed by the nightly reconciliation job", level: :debug)
+        end
+      RUBY
+    end
+
+    it 'adds parentheses so a collapsed block keeps binding to its call' do
+      write_block_delimiters('line_count_based')
+      source = <<~RUBY
+        expect page do |p|
+          p.visible?
+        end
+      RUBY
+
+      expect(Rfmt.format(source)).to eq("expect(page) { |p| p.visible? }\n")
+    end
+
+    it 'keeps do...end for blocks with rescue' do
+      write_block_delimiters('line_count_based')
+      source = <<~RUBY
+        jobs.each do |job|
+          job.run
+        rescue StandardError
+          retry
+        end
+      RUBY
+
+      expect(Rfmt.format(source)).to eq(source)
+    end
+
+    it 'uses braces for functional blocks and do...end for procedural ones with semantic' do
+      write_block_delimiters('semantic')
+      source = <<~RUBY
+        users.select do |user|
+          user.active?
+        end
+        users.each { |user| notify(user) }
+      RUBY
+
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        users.select { |user|
+          user.active?
+        }
+        users.each do |user|
+          notify(user)
+        end
+      RUBY
+    end
+
+    it 'applies the delimiters to blocks assigned to a variable' do
+      write_block_delimiters('line_count_based')
+      source = <<~RUBY
+        def names
+          result = items.map do |item|
+            item.name
+          end
+          result.sort
+        end
+      RUBY
+
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        def names
+          result = items.map { |item| item.name }
+          result.sort
+        end
+      RUBY
+    end
+
+    it 'uses braces for functional blocks on the right of an assignment with semantic' do
+      write_block_delimiters('semantic')
+      source = <<~RUBY
+        result = items.map do |x| x * 2 end
+        let(:user) do build(:user) end
+      RUBY
+
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        result = items.map { |x| x * 2 }
+        let(:user) { build(:user) }
+      RUBY
+    end
+  end
+
+  describe 'case_when' do
+    let(:case_source) do
+      <<~RUBY
+        case status
+        when :pending then process_pending
+        when :in_progress, :retrying then resume
+        else wait
+        end
+      RUBY
+    end
+
+    it 'expands one-line branches with expand' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            case_when: "expand"
+      YAML
+
+      expect(Rfmt.format(case_source)).to eq(<<~RUBY)
+        case status
+        when :pending
+          process_pending
+        when :in_progress, :retrying
+          resume
+        else
+          wait
+        end
+      RUBY
+    end
+
+    it 'aligns then across one-line branches with compact_aligned' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            case_when: "compact_aligned"
+      YAML
+
+      expect(Rfmt.format(case_source)).to eq(<<~RUBY)
+        case status
+        when :pending                then process_pending
+        when :in_progress, :retrying then resume
+        else
+          wait
+        end
+      RUBY
+    end
+  end
+
+  describe 'percent literals' do
+    it 'rewrites delimiters with percent_literal_delimiters' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            percent_literal_delimiters:
+              default: "()"
+              "%w": "[]"
+      YAML
+
+      source = <<~'RUBY'
+        NAMES = %w(alice  bob)
+        SYMBOLS = %i{read write}
+        title = %q[Rock (live)]
+        quoted = %Q{Hello #{name}}
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~'RUBY')
+        NAMES = %w[alice bob]
+        SYMBOLS = %i(read write)
+        title = %q[Rock (live)]
+        quoted = %Q(Hello #{name})
+      RUBY
+    end
+
+    it 'rewrites percent literals assigned to constants in a class body' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            percent_literal_delimiters:
+              default: "[]"
+      YAML
+
+      source = <<~RUBY
+        class Roles
+          FOO = %w(admin member)
+          BAR = %q(x)
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        class Roles
+          FOO = %w[admin member]
+          BAR = %q[x]
+        end
+      RUBY
+    end
+
+    it 'keeps percent literals as written by default' do
+      source = "NAMES = %w(alice  bob)\ntitle = %q{Rock}\n"
+      expect(Rfmt.format(source)).to eq(source)
+    end
+
+    it 'converts word arrays to %w and wraps them with word_arrays: percent' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          line_length: 40
+          style:
+            word_arrays: "percent"
+      YAML
+
+      source = <<~RUBY
+        ROLES = ['admin', "member"]
+        STATUSES = ['pending', 'active', 'suspended', 'archived', 'deleted']
+        LABELS = ['needs review', 'done']
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        ROLES = %w[admin member]
+        STATUSES = %w[
+          pending active suspended archived
+          deleted
+        ]
+        LABELS = ['needs review', 'done']
+      RUBY
+    end
+
+    it 'converts %w arrays to quoted strings with word_arrays: brackets' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            quotes: "single"
+            word_arrays: "brackets"
+      YAML
+
+      source = "ROLES = %w[admin member]\n"
+      expect(Rfmt.format(source)).to eq("ROLES = ['admin', 'member']\n")
+    end
+
+    it 'rewrites percent literals inside broken chains and collapsed blocks' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            block_delimiters: "line_count_based"
+            percent_literal_delimiters:
+              "%w": "[]"
+      YAML
+
+      source = <<~RUBY
+        User.where(active: true).where(role: %w(admin owner)).order(created_at: :desc).includes(:profile, :posts)
+        users.each do |user|
+          user.grant(%w(read write))
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        User.where(active: true)
+          .where(role: %w[admin owner])
+          .order(created_at: :desc)
+          .includes(:profile, :posts)
+        users.each { |user| user.grant(%w[read write]) }
+      RUBY
+    end
+  end
+
+  describe 'numeric literals' do
+    it 'normalizes numbers with numeric_literals' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            numeric_literals:
+              underscore_min_digits: 5
+              hex_digits: "upper"
+              prefix: "lower"
+              exponent: "lower"
+      YAML
+
+      source = <<~RUBY
+        LIMIT = 1000000
+        FEE = 10_00_000
+        SMALL = 1000
+        MASK = 0Xff_ff
+        RATE = 2.5E-3
+        MODE = 0755
+        if amount > 2500000
+          flag!
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        LIMIT = 1_000_000
+        FEE = 1_000_000
+        SMALL = 1000
+        MASK = 0xFF_FF
+        RATE = 2.5e-3
+        MODE = 0755
+        if amount > 2_500_000
+          flag!
+        end
+      RUBY
+    end
+
+    it 'keeps numbers as written by default' do
+      source = "LIMIT = 1000000\nMASK = 0Xff\n"
+      expect(Rfmt.format(source)).to eq(source)
+    end
+
+    it 'normalizes numbers assigned to constants in a module body' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            numeric_literals:
+              underscore_min_digits: 5
+      YAML
+
+      source = <<~RUBY
+        module Billing
+          LIMIT = 1000000
+          MAX_TOTAL = LIMIT * 2500000
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        module Billing
+          LIMIT = 1_000_000
+          MAX_TOTAL = LIMIT * 2_500_000
+        end
+      RUBY
+    end
+
+    it 'normalizes numbers on every emit path' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            block_delimiters: "line_count_based"
+            numeric_literals:
+              underscore_min_digits: 5
+      YAML
+
+      source = <<~RUBY
+        def page(limit = 1000000)
+          Order.where(total: 1000000..).order(created_at: :desc).includes(:customer, :line_items).limit(limit)
+        end
+        orders.each do |order|
+          order.refund(2500000)
+        end
+        expect 1000000 do |value|
+          value.positive?
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        def page(limit = 1_000_000)
+          Order.where(total: 1_000_000..)
+            .order(created_at: :desc)
+            .includes(:customer, :line_items)
+            .limit(limit)
+        end
+        orders.each { |order| order.refund(2_500_000) }
+        expect(1_000_000) { |value| value.positive? }
+      RUBY
+    end
+  end
+
+  describe 'blank_lines' do
+    let(:class_source) do
+      <<~RUBY
+        class User
+
+          include Comparable
+          attr_reader :name
+          def initialize(name)
+            @name = name
+          end
+          def to_s
+            name
+          end
+
+          private
+          def secret
+            42
+          end
+
+        end
+      RUBY
+    end
+
+    it 'keeps blank lines from the source by default, except at the body edges' do
+      expect(Rfmt.format(class_source)).to eq(<<~RUBY)
+        class User
+          include Comparable
+          attr_reader :name
+          def initialize(name)
+            @name = name
+          end
+          def to_s
+            name
+          end
+
+          private
+          def secret
+            42
+          end
+        end
+      RUBY
+    end
+
+    it 'separates methods, macros and access modifiers' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          blank_lines:
+            between_methods: true
+            after_macros: true
+            around_access_modifiers: "always"
+      YAML
+
+      expect(Rfmt.format(class_source)).to eq(<<~RUBY)
+        class User
+          include Comparable
+          attr_reader :name
+
+          def initialize(name)
+            @name = name
+          end
+
+          def to_s
+            name
+          end
+
+          private
+
+          def secret
+            42
+          end
+        end
+      RUBY
+    end
+
+    it 'removes blank lines around access modifiers with never' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          blank_lines:
+            around_access_modifiers: "never"
+      YAML
+
+      source = <<~RUBY
+        class Account
+          def balance
+            0
+          end
+
+          protected
+
+          def ledger
+            []
+          end
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        class Account
+          def balance
+            0
+          end
+          protected
+          def ledger
+            []
+          end
+        end
+      RUBY
+    end
+  end
+
+  describe 'access_modifier_indentation' do
+    let(:flat_source) do
+      <<~RUBY
+        class Session
+          def open
+            connect
+          end
+
+          private
+
+          def connect
+            true
+          end
+        end
+      RUBY
+    end
+
+    it 'outdents access modifiers with outdent' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            access_modifier_indentation: "outdent"
+      YAML
+
+      expect(Rfmt.format(flat_source)).to eq(<<~RUBY)
+        class Session
+          def open
+            connect
+          end
+
+        private
+
+          def connect
+            true
+          end
+        end
+      RUBY
+    end
+
+    it 'indents the private section with indent' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            access_modifier_indentation: "indent"
+      YAML
+
+      expect(Rfmt.format(flat_source)).to eq(<<~RUBY)
+        class Session
+          def open
+            connect
+          end
+
+          private
+
+            def connect
+              true
+            end
+        end
+      RUBY
+    end
+
+    it 'ends the indented section at public and keeps comments before end in it' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            access_modifier_indentation: "indent"
+      YAML
+
+      source = <<~RUBY
+        class Session
+          private
+
+          def connect
+            true
+          end
+
+          public
+
+          def open
+            connect
+          end
+
+          protected
+
+          def retry?
+            false
+          end
+          # TODO: back off
+        end
+      RUBY
+
+      expected = <<~RUBY
+        class Session
+          private
+
+            def connect
+              true
+            end
+
+          public
+
+          def open
+            connect
+          end
+
+          protected
+
+            def retry?
+              false
+            end
+            # TODO: back off
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(expected)
+      expect(Rfmt.format(expected)).to eq(expected)
+    end
+
+    it 'keeps the layout of the source by default' do
+      indented = <<~RUBY
+        module Helpers
+          def visible
+            1
+          end
+
+          private
+            def hidden
+              2
+            end
+        end
+      RUBY
+      outdented = <<~RUBY
+        class Session
+          def open
+            connect
+          end
+
+        protected
+
+          def connect
+            true
+          end
+        end
+      RUBY
+      expect(Rfmt.format(indented)).to eq(indented)
+      expect(Rfmt.format(outdented)).to eq(outdented)
+      expect(Rfmt.format(flat_source)).to eq(flat_source)
+    end
+  end
+
+  describe 'sort_requires' do
+    let(:requires_source) do
+      <<~RUBY
+        # Dependencies
+        require_relative 'support/helpers'
+        require 'rails'
+        # Needed for Time#iso8601
+        require 'time'
+        require 'json'
+
+        require 'zeitwerk'
+        require 'bundler'
+      RUBY
+    end
+
+    it 'keeps requires in place by default' do
+      expect(Rfmt.format(requires_source)).to eq(requires_source)
+    end
+
+    it 'sorts each run by stdlib, gems and relative paths' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            sort_requires: true
+      YAML
+
+      expect(Rfmt.format(requires_source)).to eq(<<~RUBY)
+        # Dependencies
+        require 'json'
+        # Needed for Time#iso8601
+        require 'time'
+        require 'rails'
+        require_relative 'support/helpers'
+
+        require 'bundler'
+        require 'zeitwerk'
+      RUBY
+    end
+
+    it 'sorts autoloads inside a module body' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            sort_requires: true
+      YAML
+
+      source = <<~RUBY
+        module App
+          autoload :Parser, 'app/parser'
+          autoload :Cli, 'app/cli'
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        module App
+          autoload :Cli, 'app/cli'
+          autoload :Parser, 'app/parser'
+        end
+      RUBY
+    end
+  end
+
+  describe 'gemfile' do
+    let(:gemfile_source) do
+      <<~RUBY
+        source 'https://rubygems.org'
+
+        gem 'rails','~> 7.1'
+        gem 'Puma'
+        # Background jobs
+        gem 'sidekiq' ,require:false
+        gem 'bootsnap', :require => false
+
+        group :development, :test do
+          gem 'rspec-rails'
+          gem 'debug', platforms: %i[mri windows]
+        end
+      RUBY
+    end
+
+    it 'normalizes argument spacing in Gemfiles by default' do
+      result = Rfmt.format(gemfile_source, path: 'Gemfile')
+      expect(result).to include("gem 'rails', '~> 7.1'\n")
+      expect(result).to include("gem 'sidekiq', require: false\n")
+      expect(result).to include("gem 'bootsnap', :require => false\n")
+      expect(Rfmt.format("gem 'rails','~> 7.1'\n", path: 'app.rb')).to eq("gem 'rails','~> 7.1'\n")
+    end
+
+    it 'sorts gem declarations when sort_gems is enabled' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          gemfile:
+            sort_gems: true
+      YAML
+
+      expect(Rfmt.format(gemfile_source, path: 'Gemfile')).to eq(<<~RUBY)
+        source 'https://rubygems.org'
+
+        gem 'bootsnap', :require => false
+        gem 'Puma'
+        gem 'rails', '~> 7.1'
+        # Background jobs
+        gem 'sidekiq', require: false
+
+        group :development, :test do
+          gem 'debug', platforms: %i[mri windows]
+          gem 'rspec-rails'
+        end
+      RUBY
+    end
+
+    it 'sorts each dependency method of a gemspec separately' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          gemfile:
+            sort_gems: true
+      YAML
+
+      source = <<~RUBY
+        Gem::Specification.new do |spec|
+          spec.add_dependency 'thor'
+          spec.add_dependency 'parallel'
+          spec.add_development_dependency 'rspec'
+          spec.add_development_dependency 'rake'
+        end
+      RUBY
+      expect(Rfmt.format(source, path: 'rfmt.gemspec')).to eq(<<~RUBY)
+        Gem::Specification.new do |spec|
+          spec.add_dependency 'parallel'
+          spec.add_dependency 'thor'
+          spec.add_development_dependency 'rake'
+          spec.add_development_dependency 'rspec'
+        end
+      RUBY
+    end
+
+    it 'reports duplicate gems when check_duplicates is enabled' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          gemfile:
+            check_duplicates: true
+      YAML
+
+      source = <<~RUBY
+        gem 'rails'
+
+        group :test do
+          gem 'rails'
+        end
+      RUBY
+      formatted, diagnostics = Rfmt.format_with_diagnostics(source, path: 'Gemfile')
+
+      expect(formatted).to eq(source)
+      duplicate = Rfmt::Diagnostic.new("gem 'rails' is already declared on line 1", 4, 2, 4, 13)
+      expect(diagnostics).to eq([duplicate])
+      expect(Rfmt.format_with_diagnostics(source, path: 'app.rb').last).to be_empty
+    end
+  end
+
+  describe 'insert_frozen_string_literal' do
+    before do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            insert_frozen_string_literal: true
+      YAML
+    end
+
+    it 'adds the comment after the shebang and other magic comments' do
+      source = <<~RUBY
+        #!/usr/bin/env ruby
+        # encoding: utf-8
+        puts 'hello'
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        #!/usr/bin/env ruby
+        # encoding: utf-8
+        # frozen_string_literal: true
+
+        puts 'hello'
+      RUBY
+    end
+
+    it 'keeps an existing declaration' do
+      source = "# frozen_string_literal: false\n\nputs 'hello'\n"
+      expect(Rfmt.format(source)).to eq(source)
+    end
+
+    it 'keeps an existing declaration below a header comment' do
+      source = <<~RUBY
+        # Copyright (c) Example Corp
+        # frozen_string_literal: false
+
+        puts 'hello'
+      RUBY
+      expect(Rfmt.format(source)).to eq(source)
+    end
+  end
+
+  describe 'comments' do
+    it 'adds a space after the hash and strips trailing whitespace' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          comments:
+            space_after_hash: true
+            strip_trailing_whitespace: true
+      YAML
+
+      source = "#!/usr/bin/env ruby\n#Runs the app   \nrun #now  \n"
+      expect(Rfmt.format(source)).to eq("#!/usr/bin/env ruby\n# Runs the app\nrun # now\n")
+    end
+
+    it 'reflows prose paragraphs but not tags or code examples' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          line_length: 40
+          comments:
+            reflow: true
+      YAML
+
+      source = <<~RUBY
+        class Parser
+          # Parses the input and returns the syntax tree for the given source text.
+          #
+          # @param source [String] the source text that is going to be parsed
+          # @example
+          #   Parser.new.parse("1 + 2")
+          def parse(source)
+          end
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        class Parser
+          # Parses the input and returns the
+          # syntax tree for the given source
+          # text.
+          #
+          # @param source [String] the source text that is going to be parsed
+          # @example
+          #   Parser.new.parse("1 + 2")
+          def parse(source)
+          end
+        end
+      RUBY
+    end
+
+    it 'converts =begin/=end comments to line comments' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          comments:
+            convert_block_comments: true
+      YAML
+
+      source = <<~RUBY
+        =begin
+        Legacy notes
+
+        Kept for reference
+        =end
+        run
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        # Legacy notes
+        #
+        # Kept for reference
+        run
+      RUBY
+    end
+
+    it 'aligns trailing comments on consecutive lines' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          comments:
+            align_trailing_comments: true
+      YAML
+
+      source = <<~RUBY
+        module Limits
+          MIN = 1 # lowest
+          MAXIMUM = 100 # highest
+          DEFAULT = 10    # used when unset
+
+          TIMEOUT = 5 # seconds
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        module Limits
+          MIN = 1       # lowest
+          MAXIMUM = 100 # highest
+          DEFAULT = 10  # used when unset
+
+          TIMEOUT = 5 # seconds
+        end
+      RUBY
+    end
+
+    it 'aligns trailing comments inside a multi-line hash' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          comments:
+            align_trailing_comments: true
+      YAML
+
+      source = <<~RUBY
+        LIMITS = {
+          min: 1, # lowest
+          maximum: 100,   # highest
+          default: 10 # used when unset
+        }
+      RUBY
+      expected = <<~RUBY
+        LIMITS = {
+          min: 1,       # lowest
+          maximum: 100, # highest
+          default: 10   # used when unset
+        }
+      RUBY
+      expect(Rfmt.format(source)).to eq(expected)
+      expect(Rfmt.format(expected)).to eq(expected)
+    end
+
+    it 'leaves strings that contain the text of a copied comment alone' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          comments:
+            align_trailing_comments: true
+      YAML
+
+      source = <<~RUBY
+        TAGS = {
+          a: "x   # tag",  # tag
+          bb: 2,# two
+        }
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        TAGS = {
+          a: "x   # tag", # tag
+          bb: 2,          # two
+        }
+      RUBY
+    end
+
+    it 'aligns consecutive assignments' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            align_assignments: true
+      YAML
+
+      source = <<~RUBY
+        MIN = 1
+        MAXIMUM = 100
+
+        timeout = 5
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        MIN     = 1
+        MAXIMUM = 100
+
+        timeout = 5
+      RUBY
+    end
+
+    it 'aligns constant assignments in a class body' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            align_assignments: true
+      YAML
+
+      source = <<~RUBY
+        class Limits
+          MIN = 1
+          MAXIMUM = 100
+          DEFAULT_TIMEOUT = 5
+        end
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        class Limits
+          MIN             = 1
+          MAXIMUM         = 100
+          DEFAULT_TIMEOUT = 5
+        end
+      RUBY
+    end
+
+    it 'aligns hash values in table style' do
+      File.write('rfmt.yml', <<~YAML)
+        version: "1.0"
+        formatting:
+          style:
+            align_hash_values: table
+      YAML
+
+      source = <<~RUBY
+        options = {
+          id: 1,
+          name:  'rfmt',
+          :legacy => true,
+          :v=>2
+        }
+      RUBY
+      expect(Rfmt.format(source)).to eq(<<~RUBY)
+        options = {
+          id:   1,
+          name: 'rfmt',
+          :legacy => true,
+          :v      => 2
+        }
+      RUBY
+    end
+  end
 end
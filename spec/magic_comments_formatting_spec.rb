@@ -0,0 +1,94 @@
+# frozen_string_literal: true
+
+require 'spec_helper'
+
+RSpec.describe Rfmt, 'Magic Comment Formatting' do
+  it 'orders magic comments and puts one blank line after them' do
+    source = <<~RUBY
+      # encoding: utf-8
+      # typed: strict
+      # frozen_string_literal: true
+      class Foo
+      end
+    RUBY
+    expected = <<~RUBY
+      # encoding: utf-8
+      # frozen_string_literal: true
+      # typed: strict
+
+      class Foo
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'normalizes spelling and case' do
+    source = <<~RUBY
+      # -*- coding: utf-8; frozen-string-literal: TRUE -*-
+      # Shareable_Constant_Value: Literal
+
+
+      puts 'hello'
+    RUBY
+    expected = <<~RUBY
+      # encoding: utf-8
+      # frozen_string_literal: true
+      # shareable_constant_value: literal
+
+      puts 'hello'
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'keeps the shebang first and documentation comments after the blank line' do
+    source = <<~RUBY
+      #!/usr/bin/env ruby
+      # frozen_string_literal: true
+      # Prints a greeting
+      puts 'hello'
+    RUBY
+    expected = <<~RUBY
+      #!/usr/bin/env ruby
+      # frozen_string_literal: true
+
+      # Prints a greeting
+      puts 'hello'
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'leaves files without magic comments alone' do
+    source = <<~RUBY
+      # Prints a greeting
+      # encoding: not a magic comment after a plain one
+      puts 'hello'
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+
+  it 'reads the encoding only from the first line or the line after the shebang' do
+    misplaced = <<~RUBY
+      # frozen_string_literal: true
+      # encoding: utf-8
+      puts 'hello'
+    RUBY
+    expect(Rfmt.format(misplaced)).to eq(<<~RUBY)
+      # frozen_string_literal: true
+
+      # encoding: utf-8
+      puts 'hello'
+    RUBY
+
+    after_shebang = <<~RUBY
+      #!/usr/bin/env ruby
+      # coding: utf-8
+      puts 'hello'
+    RUBY
+    expect(Rfmt.format(after_shebang)).to eq(<<~RUBY)
+      #!/usr/bin/env ruby
+      # encoding: utf-8
+
+      puts 'hello'
+    RUBY
+  end
+end
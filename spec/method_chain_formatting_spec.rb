@@ -0,0 +1,137 @@
+# frozen_string_literal: true
+
+require 'spec_helper'
+
+RSpec.describe Rfmt, 'Method Chain Formatting' do
+  it 'keeps a short chain on one line' do
+    source = "User.where(active: true).order(:name).limit(10)\n"
+    expect(Rfmt.format(source)).to eq(source)
+  end
+
+  it 'breaks a chain longer than line_length into leading-dot lines' do
+    source = <<~RUBY
+      class UsersQuery
+      def call
+      User.where(active: true, role: "admin").order(created_at: :desc).includes(:profile, :posts).limit(100)
+      end
+      end
+    RUBY
+    expected = <<~RUBY
+      class UsersQuery
+        def call
+          User.where(active: true, role: "admin")
+            .order(created_at: :desc)
+            .includes(:profile, :posts)
+            .limit(100)
+        end
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 're-indents a chain that was already broken' do
+    source = <<~RUBY
+      def recent
+      Post
+              .published
+              &.order(:created_at)
+      end
+    RUBY
+    expected = <<~RUBY
+      def recent
+        Post
+          .published
+          &.order(:created_at)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'keeps comments between chain links' do
+    source = <<~RUBY
+      def visible
+      Post.where(draft: false) # published only
+      # newest first
+      .order(created_at: :desc)
+      end
+    RUBY
+    expected = <<~RUBY
+      def visible
+        Post.where(draft: false) # published only
+          # newest first
+          .order(created_at: :desc)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks the chain before a do...end block' do
+    source = <<~RUBY
+      User.where(active: true, role: "admin").order(created_at: :desc).includes(:profile, :organization).find_each do |user|
+      notify(user)
+      end
+    RUBY
+    expected = <<~RUBY
+      User.where(active: true, role: "admin")
+        .order(created_at: :desc)
+        .includes(:profile, :organization)
+        .find_each do |user|
+        notify(user)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks a long chain assigned to a variable' do
+    source = <<~RUBY
+      def recent_users
+      scope = User.where(active: true, role: "admin").order(created_at: :desc).includes(:profile, :posts).limit(100)
+      scope.to_a
+      end
+    RUBY
+    expected = <<~RUBY
+      def recent_users
+        scope = User.where(active: true, role: "admin")
+          .order(created_at: :desc)
+          .includes(:profile, :posts)
+          .limit(100)
+        scope.to_a
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks a long chain assigned to a constant' do
+    source = <<~RUBY
+      class UsersQuery
+      ADMINS = User.where(active: true, role: "admin").order(created_at: :desc).includes(:profile, :posts).limit(100)
+      end
+    RUBY
+    expected = <<~RUBY
+      class UsersQuery
+        ADMINS = User.where(active: true, role: "admin")
+          .order(created_at: :desc)
+          .includes(:profile, :posts)
+          .limit(100)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks a long chain after return' do
+    source = <<~RUBY
+      def recent_users
+      return User.where(active: true, role: "admin").order(created_at: :desc).includes(:profile, :posts).limit(100)
+      end
+    RUBY
+    expected = <<~RUBY
+      def recent_users
+        return User.where(active: true, role: "admin")
+          .order(created_at: :desc)
+          .includes(:profile, :posts)
+          .limit(100)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+end
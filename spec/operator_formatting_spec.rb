@@ -0,0 +1,88 @@
+# frozen_string_literal: true
+
+require 'spec_helper'
+
+RSpec.describe Rfmt, 'Operator Formatting' do
+  it 'normalizes spacing around binary operators' do
+    source = "total = price+tax  *rate\n"
+    expect(Rfmt.format(source)).to eq("total = price + tax * rate\n")
+  end
+
+  it 'keeps unary operators and ** tight' do
+    source = <<~RUBY
+      area = width ** 2
+      valid = ! done  &&-offset<limit
+    RUBY
+    expected = <<~RUBY
+      area = width**2
+      valid = !done && -offset < limit
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'keeps keyword operators and parentheses' do
+    source = "ready = (a||b)&&c\nsave or  not valid?\n"
+    expect(Rfmt.format(source)).to eq("ready = (a || b) && c\nsave or not valid?\n")
+  end
+
+  it 'formats conditions of if and while' do
+    source = <<~RUBY
+      if count>0&&count<=limit
+        run
+      end
+      while queue.size>0
+        queue.pop
+      end
+    RUBY
+    expected = <<~RUBY
+      if count > 0 && count <= limit
+        run
+      end
+      while queue.size > 0
+        queue.pop
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks a long condition after its lowest-precedence operators' do
+    source = <<~RUBY
+      if user.admin? && user.active? || account.owner == current_user && account.plan.allows?(:bulk_export)
+        export!
+      end
+    RUBY
+    expected = <<~RUBY
+      if user.admin? && user.active? ||
+          account.owner == current_user && account.plan.allows?(:bulk_export)
+        export!
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'breaks a long arithmetic expression with continuation indentation' do
+    source = <<~RUBY
+      def total
+      subtotal_in_cents + shipping_in_cents + gift_wrapping_in_cents + handling_fee_in_cents - discount_in_cents
+      end
+    RUBY
+    expected = <<~RUBY
+      def total
+        subtotal_in_cents +
+          shipping_in_cents +
+          gift_wrapping_in_cents +
+          handling_fee_in_cents -
+          discount_in_cents
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'copies expressions containing comments verbatim' do
+    source = <<~RUBY
+      valid = name &&   # present
+        email
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+end
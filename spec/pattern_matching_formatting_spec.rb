@@ -0,0 +1,100 @@
+# frozen_string_literal: true
+
+require 'spec_helper'
+
+RSpec.describe Rfmt, 'Pattern Matching Formatting' do
+  it 'normalizes spacing in hash and array patterns' do
+    source = <<~RUBY
+      case data
+      in {name:String=>name,  age:}
+        greet(name)
+      in [ Integer|Float ,*rest ]
+        sum(rest)
+      end
+    RUBY
+    expected = <<~RUBY
+      case data
+      in { name: String => name, age: }
+        greet(name)
+      in [Integer | Float, *rest]
+        sum(rest)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'keeps constants, bracketless patterns and implicit rest' do
+    source = <<~RUBY
+      case shape
+      in Point( x:, y: )
+        plot(x, y)
+      in first ,*rest
+        first
+      in [ head, ]
+        head
+      end
+    RUBY
+    result = Rfmt.format(source)
+    expect(result).to include('in Point(x:, y:)')
+    expect(result).to include('in first, *rest')
+    expect(result).to include('in [head,]')
+  end
+
+  it 'normalizes guards' do
+    source = <<~RUBY
+      case pair
+      in [a,b] if a > b
+        a
+      in [a,b] unless a.nil?
+        b
+      end
+    RUBY
+    result = Rfmt.format(source)
+    expect(result).to include('in [a, b] if a > b')
+    expect(result).to include('in [a, b] unless a.nil?')
+  end
+
+  it 'breaks wide patterns one element per line' do
+    source = <<~RUBY
+      case user
+      in { name: String => name, email: String => email, roles: [*, :admin, *], address: { city: String => city } }
+        notify(email)
+      end
+    RUBY
+    expected = <<~RUBY
+      case user
+      in {
+        name: String => name,
+        email: String => email,
+        roles: [*, :admin, *],
+        address: { city: String => city }
+      }
+        notify(email)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(expected)
+  end
+
+  it 'formats one-line pattern matches' do
+    source = <<~RUBY
+      config=>{db:{user:}}
+      value in [Integer,*]
+    RUBY
+    result = Rfmt.format(source)
+    expect(result).to include('config => { db: { user: } }')
+    expect(result).to include('value in [Integer, *]')
+  end
+
+  it 'copies patterns containing comments verbatim' do
+    source = <<~RUBY
+      case data
+      in {
+        name:, # required
+        age:
+      }
+        greet(name)
+      end
+    RUBY
+    expect(Rfmt.format(source)).to eq(source)
+  end
+end
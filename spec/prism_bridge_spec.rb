@@ -11,6 +11,14 @@ RSpec.describe Rfmt::PrismBridge do
       expect { JSON.parse(result) }.not_to raise_error
     end
 
+    it 'gives constant assignments their value as a child' do
+      ast = JSON.parse(described_class.parse('LIMIT = 100'))['ast']
+      write = ast['children'].first['children'].first
+
+      expect(write['node_type']).to eq('constant_write_node')
+      expect(write['children'].map { |child| child['node_type'] }).to eq(['integer_node'])
+    end
+
     it 'raises error for invalid syntax' do
       expect do
         described_class.parse('class Foo def')
@@ -29,6 +29,20 @@ RSpec.describe Rfmt do
       end.to raise_error(Rfmt::Error)
     end
 
+    it 'raises TimeoutError when the run exceeds the timeout' do
+      source = "x = 1\n" * 5_000
+
+      expect do
+        Rfmt.format(source, timeout: 0)
+      end.to raise_error(Rfmt::TimeoutError, /Timeout/)
+    end
+
+    it 'rejects a negative timeout' do
+      expect do
+        Rfmt.format('x = 1', timeout: -1)
+      end.to raise_error(Rfmt::Error, /timeout must be a non-negative number/)
+    end
+
     it 'formats Rails migration with versioned superclass' do
       source = <<~RUBY
         class AddProfileToUsers < ActiveRecord::Migration[8.1]
@@ -76,7 +90,7 @@ RSpec.describe Rfmt do
       expect(result).to include('@timeout = timeout')
     end
 
-    it 'formats method with parameters without parentheses' do
+    it 'adds parentheses to method parameters without them' do
       source = <<~RUBY
         def foo bar, baz
           puts bar
@@ -85,10 +99,82 @@ RSpec.describe Rfmt do
 
       result = Rfmt.format(source)
 
-      expect(result).to include('def foo bar, baz')
+      expect(result).to include('def foo(bar, baz)')
       expect(result).to include('puts bar')
     end
 
+    describe 'method signatures' do
+      it 'normalizes spacing in parameter lists' do
+        source = <<~RUBY
+          def foo(a,b ,  c:1, d: , *rest,**opts,&blk)
+            a
+          end
+        RUBY
+
+        expect(Rfmt.format(source)).to include('def foo(a, b, c: 1, d:, *rest, **opts, &blk)')
+      end
+
+      it 'normalizes default values and anonymous parameters' do
+        source = <<~RUBY
+          def bar(x=1, *, **, &)
+            baz(*, **, &)
+          end
+
+          def self.forward(...)
+            target(...)
+          end
+        RUBY
+
+        result = Rfmt.format(source)
+
+        expect(result).to include('def bar(x = 1, *, **, &)')
+        expect(result).to include('def self.forward(...)')
+      end
+
+      it 'breaks a parameter list longer than line_length one per line' do
+        source = <<~RUBY
+          class Mailer
+            def deliver(recipient, subject, body, cc: [], bcc: [], reply_to: nil, timeout: 30, attachments: [], **headers)
+              send_mail
+            end
+          end
+        RUBY
+        expected = <<~RUBY
+          class Mailer
+            def deliver(
+              recipient,
+              subject,
+              body,
+              cc: [],
+              bcc: [],
+              reply_to: nil,
+              timeout: 30,
+              attachments: [],
+              **headers
+            )
+              send_mail
+            end
+          end
+        RUBY
+
+        expect(Rfmt.format(source)).to eq(expected)
+      end
+
+      it 'keeps parameter lists with comments as written' do
+        source = <<~RUBY
+          def build(name, # the record name
+                    options = {})
+            name
+          end
+        RUBY
+
+        result = Rfmt.format(source)
+
+        expect(result).to include("def build(name, # the record name\n          options = {})")
+        expect(result.scan('# the record name').size).to eq(1)
+      end
+    end
+
     it 'formats source metadata nodes' do
       source = <<~RUBY
         puts __FILE__
@@ -173,6 +259,112 @@ RSpec.describe Rfmt do
         expect(result).to include('TEXT')
       end
 
+      it 'preserves heredoc passed through nested calls' do
+        source = <<~RUBY
+          result = wrap(strip(<<~TEXT))
+            Hello World
+          TEXT
+          puts result
+        RUBY
+        result = Rfmt.format(source)
+        expect(result).to include('Hello World')
+        expect(result).to match(/^TEXT$/m)
+        expect(result).to include('puts result')
+        expect(Prism.parse(result).errors).to be_empty
+      end
+
+      it 're-indents squiggly heredoc body and terminator with the owner' do
+        source = <<~RUBY
+          class Report
+          def query
+          sql = <<~SQL
+            SELECT *
+            FROM users
+          SQL
+          run(sql)
+          end
+          end
+        RUBY
+        expected = <<~RUBY
+          class Report
+            def query
+              sql = <<~SQL
+                SELECT *
+                FROM users
+              SQL
+              run(sql)
+            end
+          end
+        RUBY
+        expect(Rfmt.format(source)).to eq(expected)
+      end
+
+      it 'keeps dash and plain heredoc bodies byte-exact' do
+        source = <<~RUBY
+          class Report
+          def text
+          a = <<-TEXT
+          Hello
+            TEXT
+          b = <<PLAIN
+          World
+          PLAIN
+          end
+          end
+        RUBY
+        result = Rfmt.format(source)
+        expect(result).to include("    a = <<-TEXT\nHello\n  TEXT\n")
+        expect(result).to include("    b = <<PLAIN\nWorld\nPLAIN\n")
+      end
+
+      it 'handles several heredocs started on one line' do
+        source = <<~RUBY
+          module Pair
+          def self.build
+          join(<<~A, <<~B)
+            first
+          A
+            second
+          B
+          end
+          end
+        RUBY
+        expected = <<~RUBY
+          module Pair
+            def self.build
+              join(<<~A, <<~B)
+                first
+              A
+                second
+              B
+            end
+          end
+        RUBY
+        expect(Rfmt.format(source)).to eq(expected)
+      end
+
+      it 'handles method calls chained after the opener' do
+        source = <<~RUBY
+          class Query
+          def sql
+          <<~SQL.strip.squeeze(" ")
+            SELECT 1
+          SQL
+          end
+          end
+        RUBY
+        expected = <<~RUBY
+          class Query
+            def sql
+              <<~SQL.strip.squeeze(" ")
+                SELECT 1
+              SQL
+            end
+          end
+        RUBY
+        expect(Rfmt.format(source)).to eq(expected)
+      end
+
       it 'preserves heredoc with interpolation' do
         source = <<~'RUBY'
           msg = <<~MSG
@@ -234,6 +426,25 @@ RSpec.describe Rfmt do
       end
     end
 
+    describe 'source encodings' do
+      it 'formats Shift_JIS source declared by a magic comment' do
+        source = "# encoding: Shift_JIS\nclass Foo\ndef bar\n'日本語' # コメント\nend\nend\n"
+                 .encode(Encoding::Shift_JIS).force_encoding(Encoding::BINARY)
+        expected = "# encoding: Shift_JIS\n\nclass Foo\n  def bar\n    '日本語' # コメント\n  end\nend\n"
+                   .encode(Encoding::Shift_JIS).force_encoding(Encoding::BINARY)
+
+        result = Rfmt.format(source)
+
+        expect(result.b).to eq(expected)
+      end
+
+      it 'rejects bytes that are invalid in the declared encoding' do
+        source = "# encoding: utf-8\nputs 'a' # \xFF\n".b
+
+        expect { Rfmt.format(source) }.to raise_error(StandardError)
+      end
+    end
+
     describe 'inline comments after blocks' do
       it 'preserves inline comments after inline brace blocks' do
         source = "b.each { p it } # c\n"
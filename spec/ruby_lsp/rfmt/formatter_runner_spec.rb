@@ -2,6 +2,8 @@
 
 require 'spec_helper'
 require 'uri'
+require 'tmpdir'
+require 'ruby_lsp/internal'
 require 'ruby_lsp/rfmt/formatter_runner'
 
 RSpec.describe RubyLsp::Rfmt::FormatterRunner do
@@ -33,6 +35,17 @@ RSpec.describe RubyLsp::Rfmt::FormatterRunner do
       end
     end
 
+    context 'when formatting runs out of time' do
+      it 'returns nil' do
+        source = "x = 1\n" * 5_000
+        document = double('Document', source: source)
+
+        result = described_class.new(timeout: 0).run_formatting(uri, document)
+
+        expect(result).to be_nil
+      end
+    end
+
     context 'with empty source' do
       it 'returns newline for empty input' do
         source = ''
@@ -46,7 +59,7 @@ RSpec.describe RubyLsp::Rfmt::FormatterRunner do
   end
 
   describe '#run_diagnostic' do
-    it 'returns empty array' do
+    it 'returns no diagnostics for ordinary code' do
       uri = URI::Generic.build(path: '/test.rb')
       document = double('Document', source: 'def foo; end')
 
@@ -54,5 +67,36 @@ RSpec.describe RubyLsp::Rfmt::FormatterRunner do
 
       expect(result).to eq([])
     end
+
+    it 'reports duplicate gems in a Gemfile' do
+      uri = URI::Generic.build(path: '/app/Gemfile')
+      document = double('Document', source: "gem 'rails'\ngem 'rails'\n")
+
+      result = Dir.mktmpdir do |dir|
+        Dir.chdir(dir) do
+          File.write('rfmt.yml', <<~YAML)
+            version: "1.0"
+            formatting:
+              gemfile:
+                check_duplicates: true
+          YAML
+          runner.run_diagnostic(uri, document)
+        end
+      end
+
+      expect(result.size).to eq(1)
+      diagnostic = result.first
+      expect(diagnostic.message).to eq("gem 'rails' is already declared on line 1")
+      expect(diagnostic.range.start.line).to eq(1)
+      expect(diagnostic.range.start.character).to eq(0)
+      expect(diagnostic.severity).to eq(RubyLsp::Constant::DiagnosticSeverity::WARNING)
+    end
+
+    it 'returns no diagnostics when the document cannot be parsed' do
+      uri = URI::Generic.build(path: '/Gemfile')
+      document = double('Document', source: 'def foo(')
+
+      expect(runner.run_diagnostic(uri, document)).to eq([])
+    end
   end
 end